@@ -0,0 +1,110 @@
+use jiff::{SignedDuration, Timestamp};
+use jsonwebtoken::{DecodingKey, EncodingKey, Header, Validation};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::{
+    auth::random_secret, config::Config, error::AppError, schemas::refresh_token::RefreshToken,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TokenType {
+    Access,
+    Refresh,
+}
+
+/// Claims carried by both access and refresh tokens.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Claims {
+    /// User ID, as a string per RFC 7519.
+    pub sub: String,
+    pub iat: i64,
+    pub exp: i64,
+    pub jti: String,
+    pub typ: TokenType,
+}
+
+impl Claims {
+    pub fn user_id(&self) -> Result<i64, AppError> {
+        self.sub.parse().map_err(|_| AppError::Unauthorized)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct TokenPair {
+    pub access_token: String,
+    pub refresh_token: String,
+    pub token_type: String,
+    /// Lifetime of the access token in seconds.
+    pub expires_in: i64,
+}
+
+/// Issue a fresh access/refresh pair and record the refresh token.
+pub async fn issue_token_pair(
+    db: &mut toasty::Db,
+    config: &Config,
+    user_id: i64,
+) -> Result<TokenPair, AppError> {
+    let (access_token, _) = encode(config, user_id, TokenType::Access, config.access_token_ttl)?;
+    let (refresh_token, claims) = encode(
+        config,
+        user_id,
+        TokenType::Refresh,
+        config.refresh_token_ttl,
+    )?;
+
+    toasty::create!(RefreshToken {
+        jti: claims.jti,
+        user_id,
+        expires_at: Timestamp::from_second(claims.exp)
+            .map_err(|e| AppError::Internal(e.to_string()))?,
+    })
+    .exec(db)
+    .await?;
+
+    Ok(TokenPair {
+        access_token,
+        refresh_token,
+        token_type: "Bearer".to_string(),
+        expires_in: config.access_token_ttl.as_secs(),
+    })
+}
+
+/// Verify the signature, expiry and type of `token`.
+pub fn decode(config: &Config, token: &str, expected: TokenType) -> Result<Claims, AppError> {
+    let claims = jsonwebtoken::decode::<Claims>(
+        token,
+        &DecodingKey::from_secret(config.jwt_secret.as_bytes()),
+        &Validation::default(),
+    )
+    .map_err(|_| AppError::Unauthorized)?
+    .claims;
+    if claims.typ != expected {
+        return Err(AppError::Unauthorized);
+    }
+    Ok(claims)
+}
+
+fn encode(
+    config: &Config,
+    user_id: i64,
+    typ: TokenType,
+    ttl: SignedDuration,
+) -> Result<(String, Claims), AppError> {
+    let now = Timestamp::now().as_second();
+    let claims = Claims {
+        sub: user_id.to_string(),
+        iat: now,
+        exp: now + ttl.as_secs(),
+        jti: random_secret(),
+        typ,
+    };
+    let token = jsonwebtoken::encode(
+        &Header::default(),
+        &claims,
+        &EncodingKey::from_secret(config.jwt_secret.as_bytes()),
+    )
+    .map_err(|e| AppError::Internal(e.to_string()))?;
+    Ok((token, claims))
+}
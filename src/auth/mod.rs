@@ -0,0 +1,189 @@
+use axum::{
+    extract::{FromRequestParts, Request, State},
+    http::{HeaderMap, Method, header, request::Parts},
+    middleware::Next,
+    response::Response,
+};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    error::AppError,
+    schemas::{
+        api_key::ApiKey,
+        user::{Role, User},
+    },
+    state::AppState,
+};
+
+pub mod jwt;
+pub mod password;
+mod role;
+
+pub use role::{Admin, MinimumRole, Moderator, RequireRole};
+
+const KEY_PREFIX: &str = "ae_";
+
+/// The authenticated caller, resolved from the `Authorization` header.
+///
+/// `user_id` is `None` for operator keys that are not bound to a user; those
+/// keys act with the [`Role::Admin`] role.
+#[derive(Debug, Clone)]
+pub struct Caller {
+    pub user_id: Option<i64>,
+    pub role: Role,
+}
+
+impl Caller {
+    /// Allow the request only if the caller is `owner_id` or at least a moderator.
+    pub fn ensure_owner(&self, owner_id: i64) -> Result<(), AppError> {
+        if self.user_id == Some(owner_id) || self.role >= Role::Moderator {
+            Ok(())
+        } else {
+            Err(AppError::Forbidden)
+        }
+    }
+
+    /// The caller's own user id, for actions only a user can take; operator
+    /// keys are refused.
+    pub fn require_user(&self) -> Result<i64, AppError> {
+        self.user_id.ok_or(AppError::Forbidden)
+    }
+
+    /// Allow the request only if the caller is `user_id` or an admin.
+    ///
+    /// Used when acting on someone's behalf, which moderation does not cover.
+    pub fn ensure_self(&self, user_id: i64) -> Result<(), AppError> {
+        if self.user_id == Some(user_id) || self.role >= Role::Admin {
+            Ok(())
+        } else {
+            Err(AppError::Forbidden)
+        }
+    }
+}
+
+impl<S> FromRequestParts<S> for Caller
+where
+    S: Send + Sync,
+{
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        parts
+            .extensions
+            .get::<Caller>()
+            .cloned()
+            .ok_or(AppError::Unauthorized)
+    }
+}
+
+/// Middleware resolving `Authorization: Bearer <token>` into a [`Caller`].
+///
+/// The token is either an API key or a JWT access token. Mutating requests
+/// are rejected without one; GET and HEAD requests pass through anonymously
+/// unless `Config::public_reads` is disabled.
+pub async fn authenticate(
+    State(mut state): State<AppState>,
+    mut req: Request,
+    next: Next,
+) -> Result<Response, AppError> {
+    let caller = match bearer_token(req.headers())? {
+        Some(token) if token.starts_with(KEY_PREFIX) => {
+            Some(resolve_api_key(&mut state.db, token).await?)
+        }
+        Some(token) => {
+            let claims = jwt::decode(&state.config, token, jwt::TokenType::Access)?;
+            Some(caller_for_user(&mut state.db, claims.user_id()?).await?)
+        }
+        None => None,
+    };
+
+    let is_read = matches!(*req.method(), Method::GET | Method::HEAD);
+    match caller {
+        Some(caller) => {
+            req.extensions_mut().insert(caller);
+        }
+        None if is_read && state.config.public_reads => {}
+        None => return Err(AppError::Unauthorized),
+    }
+
+    Ok(next.run(req).await)
+}
+
+/// Create a new API key and return it alongside the plaintext secret.
+///
+/// Only the SHA-256 hash is stored, so the secret cannot be recovered later.
+pub async fn issue_api_key(
+    db: &mut toasty::Db,
+    name: &str,
+    user_id: Option<i64>,
+) -> Result<(ApiKey, String), toasty::Error> {
+    let secret = format!("{KEY_PREFIX}{}", random_secret());
+
+    let api_key = toasty::create!(ApiKey {
+        name: name.to_string(),
+        key_hash: hash_key(&secret),
+        user_id,
+    })
+    .exec(db)
+    .await?;
+    Ok((api_key, secret))
+}
+
+async fn resolve_api_key(db: &mut toasty::Db, token: &str) -> Result<Caller, AppError> {
+    let api_key = ApiKey::filter_by_key_hash(hash_key(token))
+        .first()
+        .exec(db)
+        .await?
+        .ok_or(AppError::Unauthorized)?;
+    match api_key.user_id {
+        Some(user_id) => caller_for_user(db, user_id).await,
+        None => Ok(Caller {
+            user_id: None,
+            role: Role::Admin,
+        }),
+    }
+}
+
+async fn caller_for_user(db: &mut toasty::Db, user_id: i64) -> Result<Caller, AppError> {
+    let user = User::filter(
+        User::fields()
+            .id()
+            .eq(user_id)
+            .and(User::fields().deleted_at().is_none()),
+    )
+    .first()
+    .exec(db)
+    .await?
+    .ok_or(AppError::Unauthorized)?;
+    Ok(Caller {
+        user_id: Some(user.id),
+        role: user.role,
+    })
+}
+
+fn bearer_token(headers: &HeaderMap) -> Result<Option<&str>, AppError> {
+    let Some(value) = headers.get(header::AUTHORIZATION) else {
+        return Ok(None);
+    };
+    value
+        .to_str()
+        .ok()
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(|token| Some(token.trim()))
+        .ok_or(AppError::Unauthorized)
+}
+
+/// 32 random bytes, hex encoded.
+pub(crate) fn random_secret() -> String {
+    let mut bytes = [0u8; 32];
+    rand::fill(&mut bytes);
+    to_hex(&bytes)
+}
+
+fn hash_key(secret: &str) -> String {
+    to_hex(&Sha256::digest(secret.as_bytes()))
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
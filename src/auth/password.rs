@@ -0,0 +1,41 @@
+use argon2::{
+    Argon2,
+    password_hash::{PasswordHasher, PasswordVerifier, phc::PasswordHash},
+};
+
+use crate::error::AppError;
+
+/// An Argon2id hash with the default parameters, checked in place of a real
+/// one when a login names no account with a password.
+const DUMMY_HASH: &str = "$argon2id$v=19$m=19456,t=2,p=1$Lk61bXMak2YxhO62Bh4QfA$p7mooEbdJIB7ftf27y3T7CRd23N8SJF6WTwdzNr8/Bs";
+
+/// Hash `password` with Argon2id on the blocking pool.
+pub async fn hash_password(password: String) -> Result<String, AppError> {
+    tokio::task::spawn_blocking(move || {
+        Argon2::default()
+            .hash_password(password.as_bytes())
+            .map(|hash| hash.to_string())
+            .map_err(|e| AppError::Internal(e.to_string()))
+    })
+    .await
+    .map_err(|e| AppError::Internal(e.to_string()))?
+}
+
+/// Check `password` against a stored PHC string.
+pub async fn verify_password(password: String, hash: String) -> Result<bool, AppError> {
+    tokio::task::spawn_blocking(move || {
+        let parsed = PasswordHash::new(&hash).map_err(|e| AppError::Internal(e.to_string()))?;
+        Ok(Argon2::default()
+            .verify_password(password.as_bytes(), &parsed)
+            .is_ok())
+    })
+    .await
+    .map_err(|e| AppError::Internal(e.to_string()))?
+}
+
+/// Spend as long as [`verify_password`] would, so failing a login for an
+/// unknown account takes as long as for a wrong password.
+pub async fn verify_dummy(password: String) -> Result<(), AppError> {
+    verify_password(password, DUMMY_HASH.to_string()).await?;
+    Ok(())
+}
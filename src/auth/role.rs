@@ -0,0 +1,46 @@
+use std::marker::PhantomData;
+
+use axum::{extract::FromRequestParts, http::request::Parts};
+
+use crate::{auth::Caller, error::AppError, schemas::user::Role};
+
+/// Type-level role bound used with [`RequireRole`].
+pub trait MinimumRole {
+    const ROLE: Role;
+}
+
+/// Marker for routes restricted to moderators and admins.
+#[derive(Debug)]
+pub struct Moderator;
+
+/// Marker for routes restricted to admins.
+#[derive(Debug)]
+pub struct Admin;
+
+impl MinimumRole for Moderator {
+    const ROLE: Role = Role::Moderator;
+}
+
+impl MinimumRole for Admin {
+    const ROLE: Role = Role::Admin;
+}
+
+/// Extracts the [`Caller`] and rejects with 403 unless it holds at least `R::ROLE`.
+#[derive(Debug)]
+pub struct RequireRole<R>(pub Caller, PhantomData<R>);
+
+impl<R, S> FromRequestParts<S> for RequireRole<R>
+where
+    R: MinimumRole,
+    S: Send + Sync,
+{
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let caller = Caller::from_request_parts(parts, state).await?;
+        if caller.role < R::ROLE {
+            return Err(AppError::Forbidden);
+        }
+        Ok(Self(caller, PhantomData))
+    }
+}
@@ -0,0 +1,31 @@
+use jiff::{SignedDuration, tz::TimeZone};
+
+/// Runtime configuration shared with every handler through [`AppState`](crate::AppState).
+#[derive(Debug, Clone)]
+pub struct Config {
+    /// Allow GET/HEAD requests without an API key.
+    pub public_reads: bool,
+    /// HMAC secret used to sign JWT access and refresh tokens.
+    pub jwt_secret: String,
+    pub access_token_ttl: SignedDuration,
+    pub refresh_token_ttl: SignedDuration,
+    /// Time zone whose calendar days the joke of the day follows.
+    pub daily_joke_timezone: TimeZone,
+    /// Days before a joke of the day may be picked again.
+    pub daily_joke_window_days: u32,
+}
+
+impl Default for Config {
+    /// Defaults suitable for development; the JWT secret is random per process,
+    /// so issued tokens do not survive a restart.
+    fn default() -> Self {
+        Self {
+            public_reads: true,
+            jwt_secret: crate::auth::random_secret(),
+            access_token_ttl: SignedDuration::from_mins(15),
+            refresh_token_ttl: SignedDuration::from_hours(24 * 30),
+            daily_joke_timezone: TimeZone::UTC,
+            daily_joke_window_days: 30,
+        }
+    }
+}
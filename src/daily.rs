@@ -0,0 +1,105 @@
+//! The joke of the day.
+//!
+//! Each calendar day (in the configured time zone) gets one joke, chosen by
+//! hashing the date so every instance picks the same one, and recorded so the
+//! pick survives new jokes being added during the day. Jokes picked within the
+//! no-repeat window are skipped while any other joke is available.
+
+use jiff::{Timestamp, ToSpan, civil::Date};
+use sha2::{Digest, Sha256};
+use toasty::stmt::Expr;
+
+use crate::{
+    error::AppError,
+    request::joke_request::visible,
+    schemas::{daily_joke::DailyJoke, joke::Joke},
+    state::AppState,
+};
+
+/// Today's joke, picking and recording one if today has none yet.
+pub async fn joke_of_the_day(state: &mut AppState) -> Result<Joke, AppError> {
+    let today = Timestamp::now()
+        .to_zoned(state.config.daily_joke_timezone.clone())
+        .date();
+    let window = state.config.daily_joke_window_days;
+    let db = &mut state.db;
+
+    if let Some(pick) = DailyJoke::filter_by_day(today).first().exec(db).await? {
+        let current = Joke::filter(Joke::fields().id().eq(pick.joke_id).and(visible()))
+            .first()
+            .exec(db)
+            .await?;
+        if let Some(joke) = current {
+            return Ok(joke);
+        }
+        // The pick was deleted or hidden; choose again.
+        pick.delete().exec(db).await?;
+    }
+
+    let since = today
+        .checked_sub(i64::from(window).days())
+        .unwrap_or(Date::MIN);
+    let recent: Vec<i64> = DailyJoke::filter(
+        DailyJoke::fields()
+            .day()
+            .ge(since)
+            .and(DailyJoke::fields().day().lt(today)),
+    )
+    .exec(db)
+    .await?
+    .into_iter()
+    .map(|pick| pick.joke_id)
+    .collect();
+
+    let fresh = if recent.is_empty() {
+        visible()
+    } else {
+        Joke::fields().id().in_list(recent).not().and(visible())
+    };
+    let joke = match pick(db, today, fresh).await? {
+        Some(joke) => joke,
+        // Every joke was picked recently, so repeating one is unavoidable.
+        None => pick(db, today, visible())
+            .await?
+            .ok_or_else(|| AppError::NotFound("There are no jokes yet".to_string()))?,
+    };
+
+    let recorded = toasty::create!(DailyJoke {
+        day: today,
+        joke_id: joke.id,
+    })
+    .exec(db)
+    .await
+    .map_err(AppError::from);
+    match recorded {
+        Ok(_) => Ok(joke),
+        // Another request recorded today's pick first; it wins.
+        Err(AppError::Conflict { .. }) => {
+            let pick = DailyJoke::get_by_day(db, today).await?;
+            Ok(Joke::get_by_id(db, pick.joke_id).await?)
+        }
+        Err(err) => Err(err),
+    }
+}
+
+/// The joke matching `filter` that `day` hashes to.
+async fn pick(
+    db: &mut toasty::Db,
+    day: Date,
+    filter: Expr<bool>,
+) -> Result<Option<Joke>, AppError> {
+    let count = Joke::filter(filter.clone()).count().exec(db).await?;
+    if count == 0 {
+        return Ok(None);
+    }
+    let digest = Sha256::digest(day.to_string().as_bytes());
+    let hash = u64::from_be_bytes(digest[..8].try_into().expect("digest is 32 bytes"));
+    let offset = usize::try_from(hash % count).expect("offset is below a row count");
+    Ok(Joke::filter(filter)
+        .order_by(Joke::fields().id().asc())
+        .limit(1)
+        .offset(offset)
+        .first()
+        .exec(db)
+        .await?)
+}
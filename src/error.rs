@@ -1,37 +1,162 @@
+use std::collections::BTreeMap;
+
 use axum::{
+    Json,
     extract::rejection,
-    http::StatusCode,
+    http::{HeaderValue, StatusCode, header},
     response::{IntoResponse, Response},
 };
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
 use tracing::error;
+use utoipa::ToSchema;
+
+pub const PROBLEM_JSON: &str = "application/problem+json";
 
 #[derive(Debug, Error)]
 pub enum AppError {
     #[error(transparent)]
     Validation(#[from] validator::ValidationErrors),
     #[error(transparent)]
-    DBError(#[from] toasty::Error),
+    DBError(toasty::Error),
+    #[error("A record with this {field} already exists")]
+    Conflict { field: String },
     #[error(transparent)]
     JsonError(#[from] rejection::JsonRejection),
+    #[error(transparent)]
+    PathError(#[from] rejection::PathRejection),
+    #[error(transparent)]
+    QueryError(#[from] rejection::QueryRejection),
+    #[error("{0}")]
+    BadRequest(String),
+    #[error("{0}")]
+    NotFound(String),
+    #[error("Missing or invalid credentials")]
+    Unauthorized,
+    #[error("Invalid email or password")]
+    InvalidCredentials,
+    #[error("You do not have access to this resource")]
+    Forbidden,
+    #[error("The resource has changed since it was fetched")]
+    PreconditionFailed,
+    #[error("{0}")]
+    Internal(String),
+}
+
+impl From<toasty::Error> for AppError {
+    fn from(err: toasty::Error) -> Self {
+        match unique_violation(&err) {
+            Some(field) => Self::Conflict { field },
+            None => Self::DBError(err),
+        }
+    }
+}
+
+/// Extract the column name from a unique-constraint violation, if `err` is one.
+fn unique_violation(err: &toasty::Error) -> Option<String> {
+    if !err.is_driver_operation_failed() {
+        return None;
+    }
+    // SQLite reports "UNIQUE constraint failed: <table>.<column>[, ...]".
+    let message = err.to_string();
+    let columns = message.strip_prefix("UNIQUE constraint failed: ")?;
+    let column = columns.split([',', ':']).next()?.trim();
+    let field = column.rsplit_once('.').map_or(column, |(_, field)| field);
+    Some(field.to_string())
+}
+
+/// An RFC 7807 `application/problem+json` error body.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ProblemDetails {
+    /// Problem type URI; `about:blank` means the status code says it all.
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub title: String,
+    pub status: u16,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+    /// Validation messages keyed by request field.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub errors: Option<BTreeMap<String, Vec<String>>>,
+}
+
+impl ProblemDetails {
+    pub fn new(status: StatusCode, detail: impl Into<String>) -> Self {
+        Self {
+            kind: "about:blank".to_string(),
+            title: status.canonical_reason().unwrap_or("Error").to_string(),
+            status: status.as_u16(),
+            detail: Some(detail.into()),
+            errors: None,
+        }
+    }
+}
+
+impl IntoResponse for ProblemDetails {
+    fn into_response(self) -> Response {
+        let status = StatusCode::from_u16(self.status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+        let mut response = (status, Json(self)).into_response();
+        response
+            .headers_mut()
+            .insert(header::CONTENT_TYPE, HeaderValue::from_static(PROBLEM_JSON));
+        response
+    }
 }
 
 impl IntoResponse for AppError {
     fn into_response(self) -> Response {
-        match self {
-            Self::Validation(err) => (StatusCode::BAD_REQUEST, err.to_string()),
+        let problem = match self {
+            Self::Validation(err) => ProblemDetails {
+                errors: Some(field_errors(&err)),
+                ..ProblemDetails::new(StatusCode::BAD_REQUEST, "Request validation failed")
+            },
             Self::DBError(err) if err.is_record_not_found() => {
-                (StatusCode::NOT_FOUND, "Not found".to_string())
+                ProblemDetails::new(StatusCode::NOT_FOUND, "Not found")
             }
             Self::DBError(err) => {
                 error!("{err:?}");
-                (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    "Internal server error".to_string(),
-                )
+                ProblemDetails::new(StatusCode::INTERNAL_SERVER_ERROR, "Internal server error")
             }
-            Self::JsonError(err) => (StatusCode::BAD_REQUEST, err.to_string()),
-        }
-        .into_response()
+            Self::Conflict { .. } => ProblemDetails::new(StatusCode::CONFLICT, self.to_string()),
+            Self::JsonError(err) => ProblemDetails::new(StatusCode::BAD_REQUEST, err.body_text()),
+            // Unparsable parameters are 400s; a route/handler mismatch is a 500.
+            Self::PathError(err) => ProblemDetails::new(err.status(), err.body_text()),
+            Self::QueryError(err) => ProblemDetails::new(err.status(), err.body_text()),
+            Self::BadRequest(detail) => ProblemDetails::new(StatusCode::BAD_REQUEST, detail),
+            Self::NotFound(detail) => ProblemDetails::new(StatusCode::NOT_FOUND, detail),
+            Self::Forbidden => ProblemDetails::new(StatusCode::FORBIDDEN, self.to_string()),
+            Self::PreconditionFailed => {
+                ProblemDetails::new(StatusCode::PRECONDITION_FAILED, self.to_string())
+            }
+            Self::InvalidCredentials => {
+                ProblemDetails::new(StatusCode::UNAUTHORIZED, self.to_string())
+            }
+            Self::Internal(err) => {
+                error!("{err}");
+                ProblemDetails::new(StatusCode::INTERNAL_SERVER_ERROR, "Internal server error")
+            }
+            Self::Unauthorized => {
+                let mut response =
+                    ProblemDetails::new(StatusCode::UNAUTHORIZED, self.to_string()).into_response();
+                response
+                    .headers_mut()
+                    .insert(header::WWW_AUTHENTICATE, HeaderValue::from_static("Bearer"));
+                return response;
+            }
+        };
+        problem.into_response()
     }
 }
+
+fn field_errors(err: &validator::ValidationErrors) -> BTreeMap<String, Vec<String>> {
+    err.field_errors()
+        .into_iter()
+        .map(|(field, errors)| {
+            let messages = errors
+                .iter()
+                .map(|e| e.message.as_deref().unwrap_or(&e.code).to_string())
+                .collect();
+            (field.into_owned(), messages)
+        })
+        .collect()
+}
@@ -0,0 +1,146 @@
+use axum::{Json, extract::State, http::StatusCode};
+use jiff::Timestamp;
+use tracing::instrument;
+
+use crate::{
+    auth::{
+        jwt::{self, TokenPair, TokenType},
+        password,
+    },
+    error::{AppError, ProblemDetails},
+    request::{
+        ValidatedJson,
+        auth_request::{LoginRequest, RefreshRequest, RegisterRequest},
+    },
+    schemas::{refresh_token::RefreshToken, user::User},
+    state::AppState,
+};
+
+#[utoipa::path(
+    post,
+    path = "/auth/register",
+    tag = "Auth",
+    request_body = RegisterRequest,
+    responses(
+        (status = 201, description = "User registered", body = User),
+        (status = 400, description = "Validation error", body = ProblemDetails),
+        (status = 409, description = "Email already in use", body = ProblemDetails),
+    ),
+)]
+#[instrument(skip_all)]
+pub async fn register(
+    State(mut state): State<AppState>,
+    ValidatedJson(payload): ValidatedJson<RegisterRequest>,
+) -> Result<(StatusCode, Json<User>), AppError> {
+    let password_hash = password::hash_password(payload.password).await?;
+    let user = toasty::create!(User {
+        name: payload.name,
+        email: payload.email,
+        password_hash: Some(password_hash),
+    })
+    .exec(&mut state.db)
+    .await?;
+    Ok((StatusCode::CREATED, Json(user)))
+}
+
+#[utoipa::path(
+    post,
+    path = "/auth/login",
+    tag = "Auth",
+    request_body = LoginRequest,
+    responses(
+        (status = 200, description = "Logged in", body = TokenPair),
+        (status = 400, description = "Malformed request", body = ProblemDetails),
+        (status = 401, description = "Invalid email or password", body = ProblemDetails),
+    ),
+)]
+#[instrument(skip_all)]
+pub async fn login(
+    State(mut state): State<AppState>,
+    ValidatedJson(payload): ValidatedJson<LoginRequest>,
+) -> Result<Json<TokenPair>, AppError> {
+    let user = User::filter(
+        User::fields()
+            .email()
+            .eq(payload.email)
+            .and(User::fields().deleted_at().is_none()),
+    )
+    .first()
+    .exec(&mut state.db)
+    .await?;
+    let Some((user_id, hash)) = user.and_then(|user| Some((user.id, user.password_hash?))) else {
+        // Hash anyway, so response times do not tell which emails have accounts.
+        password::verify_dummy(payload.password).await?;
+        return Err(AppError::InvalidCredentials);
+    };
+    if !password::verify_password(payload.password, hash).await? {
+        return Err(AppError::InvalidCredentials);
+    }
+
+    let tokens = jwt::issue_token_pair(&mut state.db, &state.config, user_id).await?;
+    Ok(Json(tokens))
+}
+
+#[utoipa::path(
+    post,
+    path = "/auth/refresh",
+    tag = "Auth",
+    description = "Exchange a refresh token for a new token pair. The presented refresh token is revoked; presenting a revoked token again revokes every refresh token of that user.",
+    request_body = RefreshRequest,
+    responses(
+        (status = 200, description = "Tokens rotated", body = TokenPair),
+        (status = 400, description = "Malformed request", body = ProblemDetails),
+        (status = 401, description = "Invalid, expired or revoked refresh token", body = ProblemDetails),
+    ),
+)]
+#[instrument(skip_all)]
+pub async fn refresh(
+    State(mut state): State<AppState>,
+    ValidatedJson(payload): ValidatedJson<RefreshRequest>,
+) -> Result<Json<TokenPair>, AppError> {
+    let claims = jwt::decode(&state.config, &payload.refresh_token, TokenType::Refresh)?;
+    let user_id = claims.user_id()?;
+    let token = RefreshToken::filter_by_jti(claims.jti)
+        .first()
+        .exec(&mut state.db)
+        .await?
+        .ok_or(AppError::Unauthorized)?;
+
+    let now = Timestamp::now();
+    if token.revoked_at.is_some() {
+        revoke_all(&mut state.db, user_id, now).await?;
+        return Err(AppError::Unauthorized);
+    }
+    if token.expires_at <= now {
+        return Err(AppError::Unauthorized);
+    }
+
+    // Revoke the token only while it is still live, so that of two requests
+    // presenting it at once exactly one rotates it. Timestamps are bound as
+    // the RFC 3339 text toasty stores them as.
+    let revoked = toasty::sql::statement(
+        "UPDATE refresh_tokens SET revoked_at = ?1 WHERE id = ?2 AND revoked_at IS NULL",
+    )
+    .bind(format!("{now:.9}"))
+    .bind(token.id)
+    .exec(&mut state.db)
+    .await?;
+    if revoked != 1 {
+        revoke_all(&mut state.db, user_id, now).await?;
+        return Err(AppError::Unauthorized);
+    }
+    let tokens = jwt::issue_token_pair(&mut state.db, &state.config, user_id).await?;
+    Ok(Json(tokens))
+}
+
+/// Revoke every live refresh token of `user_id`: one was replayed, so the
+/// whole chain is compromised.
+async fn revoke_all(db: &mut toasty::Db, user_id: i64, now: Timestamp) -> Result<(), AppError> {
+    RefreshToken::filter_by_user_id(user_id)
+        .filter(RefreshToken::fields().revoked_at().is_none())
+        .update()
+        .revoked_at(Some(now))
+        .exec(db)
+        .await?;
+    Ok(())
+}
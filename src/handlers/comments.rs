@@ -0,0 +1,227 @@
+use axum::{
+    Json,
+    extract::State,
+    http::{StatusCode, Uri},
+};
+use tracing::instrument;
+
+use crate::{
+    SerializablePage,
+    auth::Caller,
+    error::{AppError, ProblemDetails},
+    handlers::jokes,
+    request::{
+        Path, Query, ValidatedJson,
+        comment_request::{CommentFilter, CommentRequest, CommentSort, CommentUpdateRequest},
+        pagination::{Paginated, PaginationParams},
+    },
+    schemas::comment::Comment,
+    state::AppState,
+};
+
+#[utoipa::path(
+    post,
+    path = "/joke/{id}/comments",
+    tag = "Comments",
+    description = "Comment on a joke as the calling user, or reply to one of its comments.",
+    security(("api_key" = [])),
+    request_body = CommentRequest,
+    params(
+        ("id" = i64, Path, description = "Joke ID"),
+    ),
+    responses(
+        (status = 201, description = "Comment created", body = Comment),
+        (status = 400, description = "Validation error, or the parent is on another joke", body = ProblemDetails),
+        (status = 401, description = "Missing or invalid credentials", body = ProblemDetails),
+        (status = 403, description = "Caller is not a user", body = ProblemDetails),
+        (status = 404, description = "Joke or parent comment not found", body = ProblemDetails),
+    ),
+)]
+#[instrument(skip(state))]
+pub async fn add_comment(
+    Path(id): Path<i64>,
+    State(mut state): State<AppState>,
+    caller: Caller,
+    ValidatedJson(payload): ValidatedJson<CommentRequest>,
+) -> Result<(StatusCode, Json<Comment>), AppError> {
+    let user_id = caller.require_user()?;
+    let joke = jokes::find_joke(&mut state.db, id).await?;
+    if let Some(parent_id) = payload.parent_id {
+        let parent = Comment::get_by_id(&mut state.db, parent_id).await?;
+        if parent.joke_id != joke.id {
+            return Err(AppError::BadRequest(
+                "The parent comment is on another joke".to_string(),
+            ));
+        }
+    }
+    let comment = toasty::create!(Comment {
+        content: payload.content,
+        joke_id: joke.id,
+        user_id,
+        parent_id: payload.parent_id,
+    })
+    .exec(&mut state.db)
+    .await?;
+    Ok((StatusCode::CREATED, Json(comment)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/joke/{id}/comments",
+    tag = "Comments",
+    description = "List a joke's top-level comments, or with `parent_id` the replies to one comment.",
+    params(
+        ("id" = i64, Path, description = "Joke ID"),
+        CommentFilter,
+        PaginationParams,
+    ),
+    responses(
+        (status = 200, description = "Paginated comments", body = SerializablePage<Comment>, headers(("Link" = String, description = "RFC 8288 links to the `next` and `prev` pages"))),
+        (status = 400, description = "Invalid pagination parameters", body = ProblemDetails),
+        (status = 404, description = "Joke not found", body = ProblemDetails),
+    ),
+)]
+#[instrument(skip(state))]
+pub async fn get_comments(
+    Path(id): Path<i64>,
+    State(mut state): State<AppState>,
+    Query(filter): Query<CommentFilter>,
+    Query(params): Query<PaginationParams>,
+    uri: Uri,
+) -> Result<Paginated<Comment>, AppError> {
+    let joke = jokes::find_joke(&mut state.db, id).await?;
+    let page = params
+        .fetch::<CommentSort>(filter.to_expr(joke.id), &mut state)
+        .await?;
+    Ok(Paginated::new(page, uri))
+}
+
+#[utoipa::path(
+    get,
+    path = "/joke/{id}/comments/{comment_id}",
+    tag = "Comments",
+    params(
+        ("id" = i64, Path, description = "Joke ID"),
+        ("comment_id" = i64, Path, description = "Comment ID"),
+    ),
+    responses(
+        (status = 200, description = "Comment found", body = Comment),
+        (status = 404, description = "Comment not found on this joke", body = ProblemDetails),
+    ),
+)]
+#[instrument(skip(state))]
+pub async fn get_comment(
+    Path((id, comment_id)): Path<(i64, i64)>,
+    State(mut state): State<AppState>,
+) -> Result<Json<Comment>, AppError> {
+    Ok(Json(find_comment(&mut state.db, id, comment_id).await?))
+}
+
+#[utoipa::path(
+    put,
+    path = "/joke/{id}/comments/{comment_id}",
+    tag = "Comments",
+    description = "Edit a comment. Only its author, moderators and admins may do this.",
+    security(("api_key" = [])),
+    request_body = CommentUpdateRequest,
+    params(
+        ("id" = i64, Path, description = "Joke ID"),
+        ("comment_id" = i64, Path, description = "Comment ID"),
+    ),
+    responses(
+        (status = 200, description = "Comment updated", body = Comment),
+        (status = 400, description = "Validation error", body = ProblemDetails),
+        (status = 401, description = "Missing or invalid credentials", body = ProblemDetails),
+        (status = 403, description = "Caller is neither the author nor a moderator", body = ProblemDetails),
+        (status = 404, description = "Comment not found on this joke", body = ProblemDetails),
+    ),
+)]
+#[instrument(skip(state))]
+pub async fn update_comment(
+    Path((id, comment_id)): Path<(i64, i64)>,
+    State(mut state): State<AppState>,
+    caller: Caller,
+    ValidatedJson(payload): ValidatedJson<CommentUpdateRequest>,
+) -> Result<Json<Comment>, AppError> {
+    let mut comment = find_comment(&mut state.db, id, comment_id).await?;
+    caller.ensure_owner(comment.user_id)?;
+    comment
+        .update()
+        .content(payload.content)
+        .exec(&mut state.db)
+        .await?;
+    Ok(Json(comment))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/joke/{id}/comments/{comment_id}",
+    tag = "Comments",
+    description = "Delete a comment and every reply beneath it. Only its author, moderators and admins may do this.",
+    security(("api_key" = [])),
+    params(
+        ("id" = i64, Path, description = "Joke ID"),
+        ("comment_id" = i64, Path, description = "Comment ID"),
+    ),
+    responses(
+        (status = 200, description = "Comment deleted"),
+        (status = 401, description = "Missing or invalid credentials", body = ProblemDetails),
+        (status = 403, description = "Caller is neither the author nor a moderator", body = ProblemDetails),
+        (status = 404, description = "Comment not found on this joke", body = ProblemDetails),
+    ),
+)]
+#[instrument(skip(state))]
+pub async fn delete_comment(
+    Path((id, comment_id)): Path<(i64, i64)>,
+    State(mut state): State<AppState>,
+    caller: Caller,
+) -> Result<StatusCode, AppError> {
+    let comment = find_comment(&mut state.db, id, comment_id).await?;
+    caller.ensure_owner(comment.user_id)?;
+    delete_threads(&mut state.db, vec![comment.id]).await?;
+    Ok(StatusCode::OK)
+}
+
+/// The comment `comment_id`, if it is on joke `joke_id`.
+async fn find_comment(
+    db: &mut toasty::Db,
+    joke_id: i64,
+    comment_id: i64,
+) -> Result<Comment, AppError> {
+    Comment::filter(
+        Comment::fields()
+            .id()
+            .eq(comment_id)
+            .and(Comment::fields().joke_id().eq(joke_id)),
+    )
+    .first()
+    .exec(db)
+    .await?
+    .ok_or_else(|| AppError::NotFound("Comment not found on this joke".to_string()))
+}
+
+/// Delete the comments `ids` together with every reply beneath them.
+///
+/// Left to itself, toasty would detach the replies and turn them into
+/// top-level comments instead.
+pub(crate) async fn delete_threads(db: &mut toasty::Db, ids: Vec<i64>) -> Result<(), AppError> {
+    let mut doomed = Vec::new();
+    let mut level = ids;
+    while !level.is_empty() {
+        doomed.extend_from_slice(&level);
+        let parents: Vec<Option<i64>> = level.into_iter().map(Some).collect();
+        level = Comment::filter(Comment::fields().parent_id().in_list(parents))
+            .exec(db)
+            .await?
+            .into_iter()
+            .map(|reply| reply.id)
+            .collect();
+    }
+    if !doomed.is_empty() {
+        Comment::filter(Comment::fields().id().in_list(doomed))
+            .delete()
+            .exec(db)
+            .await?;
+    }
+    Ok(())
+}
@@ -0,0 +1,162 @@
+use axum::{
+    Json,
+    extract::State,
+    http::{StatusCode, Uri},
+};
+use tracing::instrument;
+
+use crate::{
+    SerializablePage,
+    auth::Caller,
+    error::{AppError, ProblemDetails},
+    handlers::{jokes, users},
+    request::{
+        Path, Query,
+        joke_request::{JokeSort, visible},
+        pagination::{Paginated, PaginationParams},
+    },
+    schemas::{favorite::Favorite, joke::Joke},
+    state::AppState,
+};
+
+#[utoipa::path(
+    put,
+    path = "/joke/{id}/favorite",
+    tag = "Favorites",
+    description = "Save a joke to the calling user's favorites. Saving it again has no effect.",
+    security(("api_key" = [])),
+    params(
+        ("id" = i64, Path, description = "Joke ID"),
+    ),
+    responses(
+        (status = 200, description = "Joke is a favorite", body = Favorite),
+        (status = 401, description = "Missing or invalid credentials", body = ProblemDetails),
+        (status = 403, description = "Caller is not a user", body = ProblemDetails),
+        (status = 404, description = "Joke not found", body = ProblemDetails),
+    ),
+)]
+#[instrument(skip(state))]
+pub async fn put_favorite(
+    Path(id): Path<i64>,
+    State(mut state): State<AppState>,
+    caller: Caller,
+) -> Result<Json<Favorite>, AppError> {
+    let user_id = caller.require_user()?;
+    let joke = jokes::find_joke(&mut state.db, id).await?;
+    if let Some(favorite) = find_favorite(&mut state.db, user_id, joke.id).await? {
+        return Ok(Json(favorite));
+    }
+    let created = toasty::create!(Favorite {
+        user_id,
+        joke_id: joke.id,
+    })
+    .exec(&mut state.db)
+    .await
+    .map_err(AppError::from);
+    let favorite = match created {
+        Ok(favorite) => favorite,
+        // Saved concurrently by another request from the same user.
+        Err(AppError::Conflict { .. }) => find_favorite(&mut state.db, user_id, joke.id)
+            .await?
+            .ok_or_else(|| AppError::Internal("favorite vanished".to_string()))?,
+        Err(err) => return Err(err),
+    };
+    refresh_favorite_count(&mut state.db, joke.id).await?;
+    Ok(Json(favorite))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/joke/{id}/favorite",
+    tag = "Favorites",
+    description = "Remove a joke from the calling user's favorites.",
+    security(("api_key" = [])),
+    params(
+        ("id" = i64, Path, description = "Joke ID"),
+    ),
+    responses(
+        (status = 200, description = "Favorite removed"),
+        (status = 401, description = "Missing or invalid credentials", body = ProblemDetails),
+        (status = 403, description = "Caller is not a user", body = ProblemDetails),
+        (status = 404, description = "Joke not found, or not a favorite", body = ProblemDetails),
+    ),
+)]
+#[instrument(skip(state))]
+pub async fn delete_favorite(
+    Path(id): Path<i64>,
+    State(mut state): State<AppState>,
+    caller: Caller,
+) -> Result<StatusCode, AppError> {
+    let user_id = caller.require_user()?;
+    let joke = jokes::find_joke(&mut state.db, id).await?;
+    let favorite = find_favorite(&mut state.db, user_id, joke.id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("This joke is not one of your favorites".to_string()))?;
+    favorite.delete().exec(&mut state.db).await?;
+    refresh_favorite_count(&mut state.db, joke.id).await?;
+    Ok(StatusCode::OK)
+}
+
+#[utoipa::path(
+    get,
+    path = "/users/{user_id}/favorites",
+    tag = "Favorites",
+    params(
+        ("user_id" = i64, Path, description = "User ID"),
+        PaginationParams,
+    ),
+    responses(
+        (status = 200, description = "Paginated favorite jokes of the user", body = SerializablePage<Joke>, headers(("Link" = String, description = "RFC 8288 links to the `next` and `prev` pages"))),
+        (status = 400, description = "Invalid pagination parameters", body = ProblemDetails),
+        (status = 404, description = "User not found", body = ProblemDetails),
+    ),
+)]
+#[instrument(skip(state))]
+pub async fn get_user_favorites(
+    Path(user_id): Path<i64>,
+    State(mut state): State<AppState>,
+    Query(params): Query<PaginationParams>,
+    uri: Uri,
+) -> Result<Paginated<Joke>, AppError> {
+    let user = users::find_user(&mut state.db, user_id).await?;
+    let saved = Joke::fields()
+        .favorites()
+        .any(Favorite::fields().user_id().eq(user.id))
+        .and(visible());
+    let page = params.fetch::<JokeSort>(saved, &mut state).await?;
+    Ok(Paginated::new(page, uri))
+}
+
+async fn find_favorite(
+    db: &mut toasty::Db,
+    user_id: i64,
+    joke_id: i64,
+) -> Result<Option<Favorite>, toasty::Error> {
+    Favorite::filter(
+        Favorite::fields()
+            .user_id()
+            .eq(user_id)
+            .and(Favorite::fields().joke_id().eq(joke_id)),
+    )
+    .first()
+    .exec(db)
+    .await
+}
+
+/// Recompute a joke's denormalized favorite count, leaving `updated_at` alone.
+pub(crate) async fn refresh_favorite_count(
+    db: &mut toasty::Db,
+    joke_id: i64,
+) -> Result<(), toasty::Error> {
+    toasty::sql::statement(
+        "UPDATE jokes SET favorite_count = \
+         (SELECT count(*) FROM favorites \
+         JOIN users ON users.id = favorites.user_id \
+         WHERE favorites.joke_id = ?1 AND users.deleted_at IS NULL) \
+         WHERE id = ?1",
+    )
+    .bind(joke_id)
+    .exec(db)
+    .await?;
+    Ok(())
+}
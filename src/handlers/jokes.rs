@@ -1,18 +1,29 @@
 use axum::{
     Json,
-    extract::{Path, Query, State},
-    http::StatusCode,
+    extract::State,
+    http::{StatusCode, Uri},
+    response::{IntoResponse, Response},
 };
+use jiff::Timestamp;
+use toasty::{schema::db::Type, stmt::Value};
 use tracing::instrument;
+use validator::Validate;
 
 use crate::{
     SerializablePage,
-    error::AppError,
+    auth::{Admin, Caller, RequireRole},
+    daily,
+    error::{AppError, ProblemDetails},
+    handlers::{revisions, tags, users},
     request::{
-        ValidatedJson,
-        joke_request::{JokeRequest, PaginationParams},
+        Path, Query, ValidatedJson, ValidatedPatch,
+        conditional::{Preconditions, Tagged},
+        joke_request::{JokeFilter, JokePatchRequest, JokeRequest, JokeSort, SearchQuery, visible},
+        pagination::{Paginated, PaginationParams, SortParams},
+        prefer::ReturnPreference,
     },
     schemas::{joke::Joke, user::User},
+    search::{self, SearchHit},
     state::AppState,
 };
 
@@ -20,64 +31,191 @@ use crate::{
     post,
     path = "/users/{user_id}/jokes",
     tag = "Jokes",
+    description = "Post a joke as this user. Only the user themself and admins may do this.",
+    security(("api_key" = [])),
     request_body = JokeRequest,
     params(
         ("user_id" = i64, Path, description = "User ID"),
     ),
     responses(
         (status = 201, description = "Joke created", body = Joke),
-        (status = 404, description = "User not found", body = String),
+        (status = 400, description = "Validation error", body = ProblemDetails),
+        (status = 401, description = "Missing or invalid credentials", body = ProblemDetails),
+        (status = 403, description = "Caller is neither this user nor an admin", body = ProblemDetails),
+        (status = 404, description = "User not found", body = ProblemDetails),
     ),
 )]
 #[instrument(skip(state))]
 pub async fn add_joke(
     Path(user_id): Path<i64>,
     State(mut state): State<AppState>,
+    caller: Caller,
     ValidatedJson(payload): ValidatedJson<JokeRequest>,
 ) -> Result<(StatusCode, Json<Joke>), AppError> {
-    let user = User::get_by_id(&mut state.db, user_id).await?;
+    caller.ensure_self(user_id)?;
+    let user = users::find_user(&mut state.db, user_id).await?;
     let joke = toasty::create!(in user.jokes() {
         content: payload.content
     })
     .exec(&mut state.db)
     .await?;
+    revisions::record_original(&mut state.db, &joke).await?;
+    if let Some(names) = payload.tags {
+        tags::set_tags(&mut state.db, joke.id, names).await?;
+    }
+    let joke = tags::joke_with_tags(&mut state.db, joke.id).await?;
     Ok((StatusCode::CREATED, Json(joke)))
 }
 
+/// Fetch a joke that has not been deleted.
+pub(crate) async fn find_joke(db: &mut toasty::Db, id: i64) -> Result<Joke, AppError> {
+    Joke::filter(
+        Joke::fields()
+            .id()
+            .eq(id)
+            .and(Joke::fields().deleted_at().is_none()),
+    )
+    .first()
+    .exec(db)
+    .await?
+    .ok_or_else(|| AppError::NotFound("Not found".to_string()))
+}
+
+/// Set the content of a joke that is not deleted, or with `None` only bump
+/// its `updated_at`.
+///
+/// The `deleted_at` check is part of the write rather than a prior read, so
+/// a joke deleted after `find_joke` is `NotFound` instead of edited.
+/// Timestamps are bound as the RFC 3339 text toasty stores them as.
+pub(crate) async fn write_joke(
+    db: &mut toasty::Db,
+    id: i64,
+    content: Option<String>,
+) -> Result<(), AppError> {
+    let written = toasty::sql::statement(
+        "UPDATE jokes SET content = coalesce(?1, content), updated_at = ?2 \
+         WHERE id = ?3 AND deleted_at IS NULL",
+    )
+    .bind_typed(content.map_or(Value::Null, Value::from), Type::Text)
+    .bind(format!("{:.9}", Timestamp::now()))
+    .bind(id)
+    .exec(db)
+    .await?;
+    if written == 0 {
+        return Err(AppError::NotFound("Not found".to_string()));
+    }
+    Ok(())
+}
+
 #[utoipa::path(
     put,
     path = "/joke/{id}",
     tag = "Jokes",
+    description = "Update a joke. Only its author, moderators and admins may do this.",
+    security(("api_key" = [])),
     request_body = JokeRequest,
     params(
         ("id" = i64, Path, description = "Joke ID"),
+        ("If-Match" = Option<String>, Header, description = "Only apply the change if this matches the resource's current ETag"),
+        ("Prefer" = Option<String>, Header, description = "Send `return=minimal` to get 204 No Content instead of the updated resource"),
     ),
     responses(
-        (status = 200, description = "Joke updated"),
-        (status = 400, description = "Validation error", body = String),
+        (status = 200, description = "Joke updated", body = Joke, headers(("ETag" = String, description = "Version of the returned resource"))),
+        (status = 204, description = "Joke updated, body omitted per `Prefer: return=minimal`"),
+        (status = 400, description = "Validation error", body = ProblemDetails),
+        (status = 401, description = "Missing or invalid credentials", body = ProblemDetails),
+        (status = 403, description = "Caller is neither the author nor a moderator", body = ProblemDetails),
+        (status = 404, description = "Joke not found", body = ProblemDetails),
+        (status = 412, description = "`If-Match` does not match the current ETag", body = ProblemDetails),
     ),
 )]
 #[instrument(skip(state))]
 pub async fn update_joke(
     Path(id): Path<i64>,
     State(mut state): State<AppState>,
+    caller: Caller,
+    preconditions: Preconditions,
+    prefer: ReturnPreference,
     ValidatedJson(payload): ValidatedJson<JokeRequest>,
-) -> Result<StatusCode, AppError> {
-    Joke::update_by_id(id)
-        .content(payload.content)
-        .exec(&mut state.db)
-        .await?;
-    Ok(StatusCode::OK)
+) -> Result<Response, AppError> {
+    let joke = find_joke(&mut state.db, id).await?;
+    caller.ensure_owner(joke.user_id)?;
+    preconditions.ensure_match(&joke.etag())?;
+    revisions::set_content(&mut state.db, &joke, payload.content, caller.user_id).await?;
+    if let Some(names) = payload.tags {
+        tags::set_tags(&mut state.db, joke.id, names).await?;
+    }
+    let joke = tags::joke_with_tags(&mut state.db, joke.id).await?;
+    Ok((joke.etag(), prefer.respond(joke)).into_response())
+}
+
+#[utoipa::path(
+    patch,
+    path = "/joke/{id}",
+    tag = "Jokes",
+    description = "Update only the fields present in the body. Only its author, moderators and admins may do this. Accepts `application/json` or `application/merge-patch+json`.",
+    security(("api_key" = [])),
+    request_body(content(
+        (JokePatchRequest = "application/json"),
+        (JokePatchRequest = "application/merge-patch+json"),
+    )),
+    params(
+        ("id" = i64, Path, description = "Joke ID"),
+        ("If-Match" = Option<String>, Header, description = "Only apply the change if this matches the resource's current ETag"),
+        ("Prefer" = Option<String>, Header, description = "Send `return=minimal` to get 204 No Content instead of the updated resource"),
+    ),
+    responses(
+        (status = 200, description = "Joke updated", body = Joke, headers(("ETag" = String, description = "Version of the returned resource"))),
+        (status = 204, description = "Joke updated, body omitted per `Prefer: return=minimal`"),
+        (status = 400, description = "Validation error", body = ProblemDetails),
+        (status = 401, description = "Missing or invalid credentials", body = ProblemDetails),
+        (status = 403, description = "Caller is neither the author nor a moderator", body = ProblemDetails),
+        (status = 404, description = "Joke not found", body = ProblemDetails),
+        (status = 412, description = "`If-Match` does not match the current ETag", body = ProblemDetails),
+    ),
+)]
+#[instrument(skip(state))]
+pub async fn patch_joke(
+    Path(id): Path<i64>,
+    State(mut state): State<AppState>,
+    caller: Caller,
+    preconditions: Preconditions,
+    prefer: ReturnPreference,
+    ValidatedPatch(payload): ValidatedPatch<JokePatchRequest>,
+) -> Result<Response, AppError> {
+    let joke = find_joke(&mut state.db, id).await?;
+    caller.ensure_owner(joke.user_id)?;
+    preconditions.ensure_match(&joke.etag())?;
+    match payload.content {
+        Some(content) => {
+            revisions::set_content(&mut state.db, &joke, content, caller.user_id).await?;
+        }
+        None => write_joke(&mut state.db, joke.id, None).await?,
+    }
+    if let Some(names) = payload.tags {
+        tags::set_tags(&mut state.db, joke.id, names.unwrap_or_default()).await?;
+    }
+    let joke = tags::joke_with_tags(&mut state.db, joke.id).await?;
+    Ok((joke.etag(), prefer.respond(joke)).into_response())
 }
 
 #[utoipa::path(
     delete,
     path = "/jokes",
     tag = "Jokes",
-    responses((status = 200, description = "All jokes deleted")),
+    description = "Delete every joke. Requires the `admin` role.",
+    security(("api_key" = [])),
+    responses(
+        (status = 200, description = "All jokes deleted"),
+        (status = 401, description = "Missing or invalid credentials", body = ProblemDetails),
+        (status = 403, description = "Caller is not an admin", body = ProblemDetails),
+    ),
 )]
 #[instrument(skip(state))]
-pub async fn delete_all_jokes(State(mut state): State<AppState>) -> Result<StatusCode, AppError> {
+pub async fn delete_all_jokes(
+    State(mut state): State<AppState>,
+    _: RequireRole<Admin>,
+) -> Result<StatusCode, AppError> {
     Joke::all().delete().exec(&mut state.db).await?;
     Ok(StatusCode::OK)
 }
@@ -88,32 +226,47 @@ pub async fn delete_all_jokes(State(mut state): State<AppState>) -> Result<Statu
     tag = "Jokes",
     params(
         ("id" = i64, Path, description = "Joke ID"),
+        ("If-None-Match" = Option<String>, Header, description = "Respond 304 Not Modified if this matches the resource's current ETag"),
     ),
     responses(
-        (status = 200, description = "Joke found", body = Joke),
-        (status = 404, description = "Joke not found", body = String),
+        (status = 200, description = "Joke found", body = Joke, headers(("ETag" = String, description = "Version of the returned resource"))),
+        (status = 304, description = "Not modified since the ETag in `If-None-Match`"),
+        (status = 404, description = "Joke not found", body = ProblemDetails),
     ),
 )]
 #[instrument(skip(state))]
 pub async fn get_joke(
     Path(id): Path<i64>,
     State(mut state): State<AppState>,
-) -> Result<Json<Joke>, AppError> {
-    let joke = Joke::get_by_id(&mut state.db, id).await?;
-    Ok(Json(joke))
+    preconditions: Preconditions,
+) -> Result<Response, AppError> {
+    let joke = tags::joke_with_tags(&mut state.db, id).await?;
+    let etag = joke.etag();
+    if preconditions.not_modified(&etag) {
+        return Ok((etag, StatusCode::NOT_MODIFIED).into_response());
+    }
+    Ok((etag, Json(joke)).into_response())
 }
 
 #[utoipa::path(
     get,
     path = "/jokes",
     tag = "Jokes",
-    responses((status = 200, description = "List of all jokes", body = Vec<Joke>)),
+    description = "List every matching joke. Pagination parameters are rejected; use `/jokes/paginate` for pages.",
+    params(JokeFilter, SortParams),
+    responses(
+        (status = 200, description = "List of all jokes", body = Vec<Joke>),
+        (status = 400, description = "Invalid filter or sort, or a pagination parameter", body = ProblemDetails),
+    ),
 )]
 #[instrument(skip(state))]
-pub async fn get_all_jokes(State(mut state): State<AppState>) -> Result<Json<Vec<Joke>>, AppError> {
-    let jokes = Joke::all()
-        .order_by(Joke::fields().id().asc())
-        .exec(&mut state.db)
+pub async fn get_all_jokes(
+    State(mut state): State<AppState>,
+    Query(filter): Query<JokeFilter>,
+    Query(params): Query<SortParams>,
+) -> Result<Json<Vec<Joke>>, AppError> {
+    let jokes = params
+        .fetch_all::<JokeSort>(filter.to_expr(), &mut state)
         .await?;
     Ok(Json(jokes))
 }
@@ -124,60 +277,204 @@ pub async fn get_all_jokes(State(mut state): State<AppState>) -> Result<Json<Vec
     tag = "Jokes",
     params(
         ("user_id" = i64, Path, description = "User ID"),
+        PaginationParams,
+    ),
+    responses(
+        (status = 200, description = "Paginated jokes for the user", body = SerializablePage<Joke>, headers(("Link" = String, description = "RFC 8288 links to the `next` and `prev` pages"))),
+        (status = 400, description = "Invalid pagination parameters", body = ProblemDetails),
     ),
-    responses((status = 200, description = "List of jokes for the user", body = Vec<Joke>)),
 )]
 #[instrument(skip(state))]
 pub async fn get_user_jokes(
     Path(user_id): Path<i64>,
     State(mut state): State<AppState>,
-) -> Result<Json<Vec<Joke>>, AppError> {
-    let jokes = Joke::filter_by_user_id(user_id).exec(&mut state.db).await?;
-    Ok(Json(jokes))
+    Query(params): Query<PaginationParams>,
+    uri: Uri,
+) -> Result<Paginated<Joke>, AppError> {
+    let page = params
+        .fetch::<JokeSort>(
+            Joke::fields().user_id().eq(user_id).and(visible()),
+            &mut state,
+        )
+        .await?;
+    Ok(Paginated::new(page, uri))
 }
 
 #[utoipa::path(
     get,
     path = "/jokes/paginate",
     tag = "Jokes",
-    params(PaginationParams),
+    params(JokeFilter, PaginationParams),
     responses(
-        (status = 200, description = "Paginated jokes", body = SerializablePage<Joke>),
+        (status = 200, description = "Paginated jokes", body = SerializablePage<Joke>, headers(("Link" = String, description = "RFC 8288 links to the `next` and `prev` pages"))),
+        (status = 400, description = "Invalid pagination parameters", body = ProblemDetails),
     ),
 )]
 #[instrument(skip(state))]
 pub async fn paginate_jokes(
     State(mut state): State<AppState>,
+    Query(filter): Query<JokeFilter>,
     Query(params): Query<PaginationParams>,
-) -> Result<Json<SerializablePage<Joke>>, AppError> {
-    let query = Joke::all()
+    uri: Uri,
+) -> Result<Paginated<Joke>, AppError> {
+    let page = params
+        .fetch::<JokeSort>(filter.to_expr(), &mut state)
+        .await?;
+    Ok(Paginated::new(page, uri))
+}
+
+#[utoipa::path(
+    get,
+    path = "/jokes/random",
+    tag = "Jokes",
+    description = "A joke chosen uniformly at random from those matching the filters.",
+    params(JokeFilter),
+    responses(
+        (status = 200, description = "A random joke", body = Joke),
+        (status = 400, description = "Invalid filter", body = ProblemDetails),
+        (status = 404, description = "No joke matches the filters", body = ProblemDetails),
+    ),
+)]
+#[instrument(skip(state))]
+pub async fn random_joke(
+    State(mut state): State<AppState>,
+    Query(filter): Query<JokeFilter>,
+) -> Result<Json<Joke>, AppError> {
+    let filter = filter.to_expr();
+    let count = Joke::filter(filter.clone())
+        .count()
+        .exec(&mut state.db)
+        .await?;
+    if count == 0 {
+        return Err(AppError::NotFound(
+            "No joke matches the filters".to_string(),
+        ));
+    }
+    let offset = usize::try_from(rand::random_range(..count))
+        .map_err(|err| AppError::Internal(err.to_string()))?;
+    let joke = Joke::filter(filter)
         .order_by(Joke::fields().id().asc())
-        .paginate(params.page_size.unwrap_or(10));
-    let query = match params.cursor {
-        Some(cursor) => query.after(cursor),
-        None => query,
-    };
-    let page: SerializablePage<Joke> = query.exec(&mut state.db).await?.into();
-    Ok(Json(page))
+        .limit(1)
+        .offset(offset)
+        .first()
+        .exec(&mut state.db)
+        .await?
+        .ok_or_else(|| AppError::NotFound("No joke matches the filters".to_string()))?;
+    Ok(Json(joke))
+}
+
+#[utoipa::path(
+    get,
+    path = "/jokes/daily",
+    tag = "Jokes",
+    description = "The joke of the day: the same joke all calendar day in the server's configured time zone, not repeated within the configured number of days while other jokes remain.",
+    responses(
+        (status = 200, description = "Today's joke", body = Joke),
+        (status = 404, description = "There are no jokes yet", body = ProblemDetails),
+    ),
+)]
+#[instrument(skip(state))]
+pub async fn daily_joke(State(mut state): State<AppState>) -> Result<Json<Joke>, AppError> {
+    Ok(Json(daily::joke_of_the_day(&mut state).await?))
+}
+
+#[utoipa::path(
+    get,
+    path = "/jokes/search",
+    tag = "Jokes",
+    description = "Full-text search over joke content, best matches first. `sort` and `order` are not accepted.",
+    params(SearchQuery, PaginationParams),
+    responses(
+        (status = 200, description = "Ranked matching jokes", body = SerializablePage<SearchHit>, headers(("Link" = String, description = "RFC 8288 links to the `next` and `prev` pages"))),
+        (status = 400, description = "Invalid query or pagination parameters", body = ProblemDetails),
+    ),
+)]
+#[instrument(skip(state))]
+pub async fn search_jokes(
+    State(mut state): State<AppState>,
+    Query(query): Query<SearchQuery>,
+    Query(params): Query<PaginationParams>,
+    uri: Uri,
+) -> Result<Paginated<SearchHit>, AppError> {
+    query.validate()?;
+    let page = search::search(&query.q, &params, &mut state).await?;
+    Ok(Paginated::new(page, uri))
 }
 
 #[utoipa::path(
     delete,
     path = "/joke/{id}",
     tag = "Jokes",
+    description = "Delete a joke. It can be restored until the `purge` command removes it for good. Only its author, moderators and admins may do this.",
+    security(("api_key" = [])),
     params(
         ("id" = i64, Path, description = "Joke ID"),
+        ("If-Match" = Option<String>, Header, description = "Only apply the change if this matches the resource's current ETag"),
     ),
     responses(
         (status = 200, description = "Joke deleted"),
-        (status = 404, description = "Joke not found", body = String),
+        (status = 401, description = "Missing or invalid credentials", body = ProblemDetails),
+        (status = 403, description = "Caller is neither the author nor a moderator", body = ProblemDetails),
+        (status = 404, description = "Joke not found", body = ProblemDetails),
+        (status = 412, description = "`If-Match` does not match the current ETag", body = ProblemDetails),
     ),
 )]
 #[instrument(skip(state))]
 pub async fn delete_joke(
     Path(id): Path<i64>,
     State(mut state): State<AppState>,
+    caller: Caller,
+    preconditions: Preconditions,
 ) -> Result<StatusCode, AppError> {
-    Joke::delete_by_id(&mut state.db, id).await?;
+    let joke = find_joke(&mut state.db, id).await?;
+    caller.ensure_owner(joke.user_id)?;
+    preconditions.ensure_match(&joke.etag())?;
+    let written = toasty::sql::statement(
+        "UPDATE jokes SET deleted_at = ?1, updated_at = ?1 WHERE id = ?2 AND deleted_at IS NULL",
+    )
+    .bind(format!("{:.9}", Timestamp::now()))
+    .bind(joke.id)
+    .exec(&mut state.db)
+    .await?;
+    if written == 0 {
+        return Err(AppError::NotFound("Not found".to_string()));
+    }
     Ok(StatusCode::OK)
 }
+
+#[utoipa::path(
+    post,
+    path = "/joke/{id}/restore",
+    tag = "Jokes",
+    description = "Undo the deletion of a joke. Restoring a joke that is not deleted has no effect. Only its author, moderators and admins may do this.",
+    security(("api_key" = [])),
+    params(
+        ("id" = i64, Path, description = "Joke ID"),
+    ),
+    responses(
+        (status = 200, description = "Joke restored", body = Joke),
+        (status = 400, description = "The joke's author is deleted; restore them instead", body = ProblemDetails),
+        (status = 401, description = "Missing or invalid credentials", body = ProblemDetails),
+        (status = 403, description = "Caller is neither the author nor a moderator", body = ProblemDetails),
+        (status = 404, description = "Joke not found, or already purged", body = ProblemDetails),
+    ),
+)]
+#[instrument(skip(state))]
+pub async fn restore_joke(
+    Path(id): Path<i64>,
+    State(mut state): State<AppState>,
+    caller: Caller,
+) -> Result<Json<Joke>, AppError> {
+    let mut joke = Joke::get_by_id(&mut state.db, id).await?;
+    caller.ensure_owner(joke.user_id)?;
+    if joke.deleted_at.is_some() {
+        let author = User::get_by_id(&mut state.db, joke.user_id).await?;
+        if author.deleted_at.is_some() {
+            return Err(AppError::BadRequest(
+                "The joke's author is deleted; restore them instead".to_string(),
+            ));
+        }
+        joke.update().deleted_at(None).exec(&mut state.db).await?;
+    }
+    Ok(Json(tags::joke_with_tags(&mut state.db, joke.id).await?))
+}
@@ -1,3 +1,11 @@
+pub mod auth;
+pub mod comments;
+pub mod favorites;
 pub mod health;
 pub mod jokes;
+pub mod reactions;
+pub mod reports;
+pub mod revisions;
+pub mod tags;
 pub mod users;
+pub mod votes;
@@ -0,0 +1,145 @@
+use axum::{Json, extract::State, http::StatusCode};
+use toasty::stmt::Expr;
+use tracing::instrument;
+
+use crate::{
+    auth::Caller,
+    error::{AppError, ProblemDetails},
+    handlers::jokes,
+    request::Path,
+    schemas::reaction::{Reaction, ReactionKind},
+    state::AppState,
+};
+
+#[utoipa::path(
+    put,
+    path = "/joke/{id}/reactions/{kind}",
+    tag = "Reactions",
+    description = "React to a joke as the calling user. Reacting twice with the same kind has no effect.",
+    security(("api_key" = [])),
+    params(
+        ("id" = i64, Path, description = "Joke ID"),
+        ("kind" = ReactionKind, Path, description = "Reaction kind"),
+    ),
+    responses(
+        (status = 200, description = "Reaction recorded", body = Reaction),
+        (status = 400, description = "Unknown reaction kind", body = ProblemDetails),
+        (status = 401, description = "Missing or invalid credentials", body = ProblemDetails),
+        (status = 403, description = "Caller is not a user", body = ProblemDetails),
+        (status = 404, description = "Joke not found", body = ProblemDetails),
+    ),
+)]
+#[instrument(skip(state))]
+pub async fn put_reaction(
+    Path((id, kind)): Path<(i64, String)>,
+    State(mut state): State<AppState>,
+    caller: Caller,
+) -> Result<Json<Reaction>, AppError> {
+    let kind = parse_kind(&kind)?;
+    let user_id = caller.require_user()?;
+    let joke = jokes::find_joke(&mut state.db, id).await?;
+    if let Some(reaction) = find_reaction(&mut state.db, user_id, joke.id, kind).await? {
+        return Ok(Json(reaction));
+    }
+    let created = toasty::create!(Reaction {
+        user_id,
+        joke_id: joke.id,
+        kind: kind.as_str().to_string(),
+    })
+    .exec(&mut state.db)
+    .await
+    .map_err(AppError::from);
+    let reaction = match created {
+        Ok(reaction) => reaction,
+        // Recorded concurrently by another request from the same user.
+        Err(AppError::Conflict { .. }) => find_reaction(&mut state.db, user_id, joke.id, kind)
+            .await?
+            .ok_or_else(|| AppError::Internal("reaction vanished".to_string()))?,
+        Err(err) => return Err(err),
+    };
+    refresh_reaction_counts(&mut state.db, joke.id).await?;
+    Ok(Json(reaction))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/joke/{id}/reactions/{kind}",
+    tag = "Reactions",
+    description = "Take back the calling user's reaction of this kind.",
+    security(("api_key" = [])),
+    params(
+        ("id" = i64, Path, description = "Joke ID"),
+        ("kind" = ReactionKind, Path, description = "Reaction kind"),
+    ),
+    responses(
+        (status = 200, description = "Reaction removed"),
+        (status = 400, description = "Unknown reaction kind", body = ProblemDetails),
+        (status = 401, description = "Missing or invalid credentials", body = ProblemDetails),
+        (status = 403, description = "Caller is not a user", body = ProblemDetails),
+        (status = 404, description = "Joke or reaction not found", body = ProblemDetails),
+    ),
+)]
+#[instrument(skip(state))]
+pub async fn delete_reaction(
+    Path((id, kind)): Path<(i64, String)>,
+    State(mut state): State<AppState>,
+    caller: Caller,
+) -> Result<StatusCode, AppError> {
+    let kind = parse_kind(&kind)?;
+    let user_id = caller.require_user()?;
+    let joke = jokes::find_joke(&mut state.db, id).await?;
+    let reaction = find_reaction(&mut state.db, user_id, joke.id, kind)
+        .await?
+        .ok_or_else(|| {
+            AppError::NotFound("You have not reacted to this joke that way".to_string())
+        })?;
+    reaction.delete().exec(&mut state.db).await?;
+    refresh_reaction_counts(&mut state.db, joke.id).await?;
+    Ok(StatusCode::OK)
+}
+
+fn parse_kind(name: &str) -> Result<ReactionKind, AppError> {
+    ReactionKind::parse(name).ok_or_else(|| {
+        let kinds: Vec<&str> = ReactionKind::ALL.iter().map(|kind| kind.as_str()).collect();
+        AppError::BadRequest(format!(
+            "Unknown reaction `{name}`; expected one of: {}",
+            kinds.join(", ")
+        ))
+    })
+}
+
+async fn find_reaction(
+    db: &mut toasty::Db,
+    user_id: i64,
+    joke_id: i64,
+    kind: ReactionKind,
+) -> Result<Option<Reaction>, toasty::Error> {
+    Reaction::filter(Expr::and_all([
+        Reaction::fields().user_id().eq(user_id),
+        Reaction::fields().joke_id().eq(joke_id),
+        Reaction::fields().kind().eq(kind.as_str()),
+    ]))
+    .first()
+    .exec(db)
+    .await
+}
+
+/// Recompute a joke's denormalized reaction counts, leaving `updated_at` alone.
+pub(crate) async fn refresh_reaction_counts(
+    db: &mut toasty::Db,
+    joke_id: i64,
+) -> Result<(), toasty::Error> {
+    toasty::sql::statement(
+        "UPDATE jokes SET reaction_counts = \
+         (SELECT json_group_object(kind, n) FROM \
+         (SELECT reactions.kind, count(*) AS n FROM reactions \
+         JOIN users ON users.id = reactions.user_id \
+         WHERE reactions.joke_id = ?1 AND users.deleted_at IS NULL \
+         GROUP BY reactions.kind)) \
+         WHERE id = ?1",
+    )
+    .bind(joke_id)
+    .exec(db)
+    .await?;
+    Ok(())
+}
@@ -0,0 +1,228 @@
+use axum::{
+    Json,
+    extract::State,
+    http::{StatusCode, Uri},
+};
+use jiff::Timestamp;
+use tracing::instrument;
+
+use crate::{
+    SerializablePage,
+    auth::{Caller, Moderator, RequireRole},
+    error::{AppError, ProblemDetails},
+    handlers::jokes,
+    request::{
+        Path, Query, ValidatedJson,
+        pagination::{Paginated, PaginationParams},
+        report_request::{ReportFilter, ReportRequest, ReportSort, ResolveReportRequest},
+    },
+    schemas::{
+        joke::Joke,
+        report::{Report, ReportStatus},
+    },
+    state::AppState,
+};
+
+#[utoipa::path(
+    post,
+    path = "/joke/{id}/report",
+    tag = "Reports",
+    description = "Report a joke to the moderators as the calling user.",
+    security(("api_key" = [])),
+    request_body = ReportRequest,
+    params(
+        ("id" = i64, Path, description = "Joke ID"),
+    ),
+    responses(
+        (status = 201, description = "Report filed", body = Report),
+        (status = 400, description = "Validation error", body = ProblemDetails),
+        (status = 401, description = "Missing or invalid credentials", body = ProblemDetails),
+        (status = 403, description = "Caller is not a user", body = ProblemDetails),
+        (status = 404, description = "Joke not found", body = ProblemDetails),
+    ),
+)]
+#[instrument(skip(state))]
+pub async fn report_joke(
+    Path(id): Path<i64>,
+    State(mut state): State<AppState>,
+    caller: Caller,
+    ValidatedJson(payload): ValidatedJson<ReportRequest>,
+) -> Result<(StatusCode, Json<Report>), AppError> {
+    let reporter_id = caller.require_user()?;
+    let joke = jokes::find_joke(&mut state.db, id).await?;
+    let report = toasty::create!(Report {
+        reporter_id,
+        joke_id: joke.id,
+        reason: payload.reason,
+        note: payload.note,
+    })
+    .exec(&mut state.db)
+    .await?;
+    Ok((StatusCode::CREATED, Json(report)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/reports",
+    tag = "Reports",
+    description = "The moderation queue: open reports, oldest first, unless filtered otherwise. Only moderators and admins may do this.",
+    security(("api_key" = [])),
+    params(
+        ReportFilter,
+        PaginationParams,
+    ),
+    responses(
+        (status = 200, description = "Paginated reports", body = SerializablePage<Report>, headers(("Link" = String, description = "RFC 8288 links to the `next` and `prev` pages"))),
+        (status = 400, description = "Invalid filter or pagination parameters", body = ProblemDetails),
+        (status = 401, description = "Missing or invalid credentials", body = ProblemDetails),
+        (status = 403, description = "Caller is not a moderator", body = ProblemDetails),
+    ),
+)]
+#[instrument(skip(state))]
+pub async fn get_reports(
+    State(mut state): State<AppState>,
+    _: RequireRole<Moderator>,
+    Query(filter): Query<ReportFilter>,
+    Query(params): Query<PaginationParams>,
+    uri: Uri,
+) -> Result<Paginated<Report>, AppError> {
+    let page = params
+        .fetch::<ReportSort>(filter.to_expr(), &mut state)
+        .await?;
+    Ok(Paginated::new(page, uri))
+}
+
+#[utoipa::path(
+    post,
+    path = "/reports/{report_id}/resolve",
+    tag = "Reports",
+    description = "Close an open report. With `hide_joke`, also hide the reported joke and close its other open reports. Only moderators and admins may do this.",
+    security(("api_key" = [])),
+    request_body = ResolveReportRequest,
+    params(
+        ("report_id" = i64, Path, description = "Report ID"),
+    ),
+    responses(
+        (status = 200, description = "Report closed", body = Report),
+        (status = 400, description = "Validation error, or the report is already closed", body = ProblemDetails),
+        (status = 401, description = "Missing or invalid credentials", body = ProblemDetails),
+        (status = 403, description = "Caller is not a moderator", body = ProblemDetails),
+        (status = 404, description = "Report not found, or `hide_joke` was asked for a deleted joke", body = ProblemDetails),
+    ),
+)]
+#[instrument(skip(state))]
+pub async fn resolve_report(
+    Path(report_id): Path<i64>,
+    State(mut state): State<AppState>,
+    moderator: RequireRole<Moderator>,
+    ValidatedJson(payload): ValidatedJson<ResolveReportRequest>,
+) -> Result<Json<Report>, AppError> {
+    let moderator_id = moderator.0.user_id;
+    let mut report = Report::get_by_id(&mut state.db, report_id).await?;
+    if report.status != ReportStatus::Open {
+        return Err(AppError::BadRequest(
+            "This report is already closed".to_string(),
+        ));
+    }
+    // Hide first, so a joke deleted since it was reported fails the request
+    // before anything is written.
+    if payload.hide_joke {
+        set_hidden(&mut state.db, report.joke_id, true).await?;
+    }
+    close(&mut state.db, &mut report, payload.status, moderator_id).await?;
+
+    if payload.hide_joke {
+        // Hiding the joke answers every other complaint about it too.
+        let others = Report::filter(
+            Report::fields()
+                .joke_id()
+                .eq(report.joke_id)
+                .and(Report::fields().status().eq(ReportStatus::Open)),
+        )
+        .exec(&mut state.db)
+        .await?;
+        for mut other in others {
+            close(
+                &mut state.db,
+                &mut other,
+                ReportStatus::Resolved,
+                moderator_id,
+            )
+            .await?;
+        }
+    }
+    Ok(Json(report))
+}
+
+#[utoipa::path(
+    put,
+    path = "/joke/{id}/hidden",
+    tag = "Reports",
+    description = "Hide a joke from every joke listing. It stays reachable by id. Only moderators and admins may do this.",
+    security(("api_key" = [])),
+    params(
+        ("id" = i64, Path, description = "Joke ID"),
+    ),
+    responses(
+        (status = 200, description = "Joke hidden", body = Joke),
+        (status = 401, description = "Missing or invalid credentials", body = ProblemDetails),
+        (status = 403, description = "Caller is not a moderator", body = ProblemDetails),
+        (status = 404, description = "Joke not found", body = ProblemDetails),
+    ),
+)]
+#[instrument(skip(state))]
+pub async fn hide_joke(
+    Path(id): Path<i64>,
+    State(mut state): State<AppState>,
+    _: RequireRole<Moderator>,
+) -> Result<Json<Joke>, AppError> {
+    Ok(Json(set_hidden(&mut state.db, id, true).await?))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/joke/{id}/hidden",
+    tag = "Reports",
+    description = "Show a hidden joke in joke listings again. Only moderators and admins may do this.",
+    security(("api_key" = [])),
+    params(
+        ("id" = i64, Path, description = "Joke ID"),
+    ),
+    responses(
+        (status = 200, description = "Joke visible", body = Joke),
+        (status = 401, description = "Missing or invalid credentials", body = ProblemDetails),
+        (status = 403, description = "Caller is not a moderator", body = ProblemDetails),
+        (status = 404, description = "Joke not found", body = ProblemDetails),
+    ),
+)]
+#[instrument(skip(state))]
+pub async fn unhide_joke(
+    Path(id): Path<i64>,
+    State(mut state): State<AppState>,
+    _: RequireRole<Moderator>,
+) -> Result<Json<Joke>, AppError> {
+    Ok(Json(set_hidden(&mut state.db, id, false).await?))
+}
+
+async fn close(
+    db: &mut toasty::Db,
+    report: &mut Report,
+    status: ReportStatus,
+    moderator_id: Option<i64>,
+) -> Result<(), toasty::Error> {
+    report
+        .update()
+        .status(status)
+        .resolved_by(moderator_id)
+        .resolved_at(Some(Timestamp::now()))
+        .exec(db)
+        .await
+}
+
+async fn set_hidden(db: &mut toasty::Db, joke_id: i64, hidden: bool) -> Result<Joke, AppError> {
+    let mut joke = jokes::find_joke(db, joke_id).await?;
+    if joke.hidden != hidden {
+        joke.update().hidden(hidden).exec(db).await?;
+    }
+    Ok(joke)
+}
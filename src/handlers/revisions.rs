@@ -0,0 +1,141 @@
+use axum::{
+    Json,
+    extract::State,
+    http::Uri,
+    response::{IntoResponse, Response},
+};
+use jiff::Timestamp;
+use tracing::instrument;
+
+use crate::{
+    SerializablePage,
+    auth::Caller,
+    error::{AppError, ProblemDetails},
+    handlers::{jokes, tags},
+    request::{
+        Path, Query,
+        conditional::{Preconditions, Tagged},
+        pagination::{Paginated, PaginationParams},
+        revision_request::RevisionSort,
+    },
+    schemas::{joke::Joke, joke_revision::JokeRevision},
+    state::AppState,
+};
+
+#[utoipa::path(
+    get,
+    path = "/joke/{id}/revisions",
+    tag = "Revisions",
+    description = "List the versions of a joke's content, oldest first by default. The newest is the current content.",
+    params(
+        ("id" = i64, Path, description = "Joke ID"),
+        PaginationParams,
+    ),
+    responses(
+        (status = 200, description = "Paginated revisions", body = SerializablePage<JokeRevision>, headers(("Link" = String, description = "RFC 8288 links to the `next` and `prev` pages"))),
+        (status = 400, description = "Invalid pagination parameters", body = ProblemDetails),
+        (status = 404, description = "Joke not found", body = ProblemDetails),
+    ),
+)]
+#[instrument(skip(state))]
+pub async fn get_revisions(
+    Path(id): Path<i64>,
+    State(mut state): State<AppState>,
+    Query(params): Query<PaginationParams>,
+    uri: Uri,
+) -> Result<Paginated<JokeRevision>, AppError> {
+    let joke = jokes::find_joke(&mut state.db, id).await?;
+    let page = params
+        .fetch::<RevisionSort>(JokeRevision::fields().joke_id().eq(joke.id), &mut state)
+        .await?;
+    Ok(Paginated::new(page, uri))
+}
+
+#[utoipa::path(
+    post,
+    path = "/joke/{id}/revisions/{rev}/revert",
+    tag = "Revisions",
+    description = "Restore the content of an earlier revision, recorded as a new revision. Only the joke's author, moderators and admins may do this.",
+    security(("api_key" = [])),
+    params(
+        ("id" = i64, Path, description = "Joke ID"),
+        ("rev" = i64, Path, description = "Revision ID"),
+        ("If-Match" = Option<String>, Header, description = "Only apply the change if this matches the resource's current ETag"),
+    ),
+    responses(
+        (status = 200, description = "Joke reverted", body = Joke, headers(("ETag" = String, description = "Version of the returned resource"))),
+        (status = 401, description = "Missing or invalid credentials", body = ProblemDetails),
+        (status = 403, description = "Caller is neither the author nor a moderator", body = ProblemDetails),
+        (status = 404, description = "Joke not found, or the revision is not one of its own", body = ProblemDetails),
+        (status = 412, description = "`If-Match` does not match the current ETag", body = ProblemDetails),
+    ),
+)]
+#[instrument(skip(state))]
+pub async fn revert_revision(
+    Path((id, rev)): Path<(i64, i64)>,
+    State(mut state): State<AppState>,
+    caller: Caller,
+    preconditions: Preconditions,
+) -> Result<Response, AppError> {
+    let joke = jokes::find_joke(&mut state.db, id).await?;
+    caller.ensure_owner(joke.user_id)?;
+    preconditions.ensure_match(&joke.etag())?;
+    let revision = JokeRevision::filter(
+        JokeRevision::fields()
+            .id()
+            .eq(rev)
+            .and(JokeRevision::fields().joke_id().eq(joke.id)),
+    )
+    .first()
+    .exec(&mut state.db)
+    .await?
+    .ok_or_else(|| AppError::NotFound("Revision not found on this joke".to_string()))?;
+    set_content(&mut state.db, &joke, revision.content, caller.user_id).await?;
+    let joke = tags::joke_with_tags(&mut state.db, joke.id).await?;
+    Ok((joke.etag(), Json(joke)).into_response())
+}
+
+/// Record a joke's current content as its first revision, written when the
+/// joke last changed: at creation, or for a joke from before revisions were
+/// kept, at its latest edit.
+pub(crate) async fn record_original(db: &mut toasty::Db, joke: &Joke) -> Result<(), AppError> {
+    toasty::create!(JokeRevision {
+        joke_id: joke.id,
+        content: joke.content.clone(),
+        author_id: Some(joke.user_id),
+        written_at: joke.updated_at,
+    })
+    .exec(db)
+    .await?;
+    Ok(())
+}
+
+/// Replace a joke's content on behalf of `author_id`, recording a revision
+/// if the text actually changed. `joke` is the version the change was made
+/// against.
+pub(crate) async fn set_content(
+    db: &mut toasty::Db,
+    joke: &Joke,
+    content: String,
+    author_id: Option<i64>,
+) -> Result<(), AppError> {
+    jokes::write_joke(db, joke.id, Some(content.clone())).await?;
+    if content != joke.content {
+        let recorded = JokeRevision::filter(JokeRevision::fields().joke_id().eq(joke.id))
+            .count()
+            .exec(db)
+            .await?;
+        if recorded == 0 {
+            record_original(db, joke).await?;
+        }
+        toasty::create!(JokeRevision {
+            joke_id: joke.id,
+            content,
+            author_id,
+            written_at: Timestamp::now(),
+        })
+        .exec(db)
+        .await?;
+    }
+    Ok(())
+}
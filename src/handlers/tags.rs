@@ -0,0 +1,140 @@
+use axum::{Json, extract::State, http::Uri};
+use toasty::stmt::{Type, Value};
+use tracing::instrument;
+
+use crate::{
+    SerializablePage,
+    error::{AppError, ProblemDetails},
+    request::{
+        Path, Query,
+        joke_request::{JokeSort, visible},
+        pagination::{Paginated, PaginationParams},
+    },
+    schemas::{
+        joke::Joke,
+        joke_tag::JokeTag,
+        tag::{Tag, TagUsage},
+    },
+    state::AppState,
+};
+
+#[utoipa::path(
+    get,
+    path = "/tags",
+    tag = "Tags",
+    description = "Every tag in use, most used first.",
+    responses(
+        (status = 200, description = "Tags with the number of jokes carrying each", body = Vec<TagUsage>),
+    ),
+)]
+#[instrument(skip(state))]
+pub async fn get_all_tags(
+    State(mut state): State<AppState>,
+) -> Result<Json<Vec<TagUsage>>, AppError> {
+    let rows = toasty::sql::query(
+        "SELECT tags.name, count(*) FROM tags \
+         JOIN joke_tags ON joke_tags.tag_id = tags.id \
+         JOIN jokes ON jokes.id = joke_tags.joke_id \
+         WHERE NOT jokes.hidden AND jokes.deleted_at IS NULL \
+         GROUP BY tags.id ORDER BY count(*) DESC, tags.name",
+    )
+    .column_types([Type::String, Type::I64])
+    .exec(&mut state.db)
+    .await?;
+    let tags = rows
+        .iter()
+        .map(
+            |row| match row.as_record().map(|record| record.as_slice()) {
+                Some([Value::String(name), Value::I64(count)]) => Ok(TagUsage {
+                    name: name.clone(),
+                    jokes: u64::try_from(*count).unwrap_or_default(),
+                }),
+                _ => Err(AppError::Internal(format!("unexpected tag row: {row:?}"))),
+            },
+        )
+        .collect::<Result<_, _>>()?;
+    Ok(Json(tags))
+}
+
+#[utoipa::path(
+    get,
+    path = "/tags/{name}/jokes",
+    tag = "Tags",
+    params(
+        ("name" = String, Path, description = "Tag name"),
+        PaginationParams,
+    ),
+    responses(
+        (status = 200, description = "Paginated jokes with the tag", body = SerializablePage<Joke>, headers(("Link" = String, description = "RFC 8288 links to the `next` and `prev` pages"))),
+        (status = 400, description = "Invalid pagination parameters", body = ProblemDetails),
+        (status = 404, description = "Tag not found", body = ProblemDetails),
+    ),
+)]
+#[instrument(skip(state))]
+pub async fn get_tag_jokes(
+    Path(name): Path<String>,
+    State(mut state): State<AppState>,
+    Query(params): Query<PaginationParams>,
+    uri: Uri,
+) -> Result<Paginated<Joke>, AppError> {
+    let tag = Tag::get_by_name(&mut state.db, name).await?;
+    let tagged = Joke::fields()
+        .joke_tags()
+        .any(JokeTag::fields().tag_id().eq(tag.id))
+        .and(visible());
+    let page = params.fetch::<JokeSort>(tagged, &mut state).await?;
+    Ok(Paginated::new(page, uri))
+}
+
+/// Fetch a joke that has not been deleted, with its tags loaded.
+pub(crate) async fn joke_with_tags(db: &mut toasty::Db, id: i64) -> Result<Joke, AppError> {
+    Joke::filter(
+        Joke::fields()
+            .id()
+            .eq(id)
+            .and(Joke::fields().deleted_at().is_none()),
+    )
+    .include(Joke::fields().tags())
+    .first()
+    .exec(db)
+    .await?
+    .ok_or_else(|| AppError::NotFound("Not found".to_string()))
+}
+
+/// Replace a joke's tags with `names`, creating tags that do not exist yet.
+pub(crate) async fn set_tags(
+    db: &mut toasty::Db,
+    joke_id: i64,
+    mut names: Vec<String>,
+) -> Result<(), AppError> {
+    names.sort();
+    names.dedup();
+    JokeTag::filter(JokeTag::fields().joke_id().eq(joke_id))
+        .delete()
+        .exec(db)
+        .await?;
+    for name in names {
+        let tag = match Tag::filter_by_name(&name).first().exec(db).await? {
+            Some(tag) => tag,
+            None => {
+                let created = toasty::create!(Tag { name: name.clone() })
+                    .exec(db)
+                    .await
+                    .map_err(AppError::from);
+                match created {
+                    Ok(tag) => tag,
+                    // Created concurrently by another request.
+                    Err(AppError::Conflict { .. }) => Tag::get_by_name(db, &name).await?,
+                    Err(err) => return Err(err),
+                }
+            }
+        };
+        toasty::create!(JokeTag {
+            joke_id,
+            tag_id: tag.id,
+        })
+        .exec(db)
+        .await?;
+    }
+    Ok(())
+}
@@ -1,14 +1,26 @@
 use axum::{
     Json,
-    extract::{Path, State},
-    http::StatusCode,
+    extract::State,
+    http::{StatusCode, Uri},
+    response::{IntoResponse, Response},
 };
+use jiff::Timestamp;
+use toasty::{schema::db::Type, stmt::Value};
 use tracing::instrument;
 
 use crate::{
-    error::AppError,
-    request::{ValidatedJson, user_request::UserRequest},
-    schemas::user::User,
+    SerializablePage,
+    auth::{Admin, Caller, RequireRole},
+    error::{AppError, ProblemDetails},
+    handlers::{favorites, reactions, votes},
+    request::{
+        Path, Query, ValidatedJson, ValidatedPatch,
+        conditional::{Preconditions, Tagged},
+        pagination::{Paginated, PaginationParams},
+        prefer::ReturnPreference,
+        user_request::{RoleRequest, UserPatchRequest, UserRequest, UserSort},
+    },
+    schemas::{favorite::Favorite, joke::Joke, reaction::Reaction, user::User, vote::Vote},
     state::AppState,
 };
 
@@ -16,15 +28,21 @@ use crate::{
     post,
     path = "/users",
     tag = "Users",
+    description = "Create a user without a password. Requires the `admin` role; anyone can sign up through `/auth/register`.",
+    security(("api_key" = [])),
     request_body = UserRequest,
     responses(
         (status = 201, description = "User created", body = User),
-        (status = 400, description = "Validation error", body = String),
+        (status = 400, description = "Validation error", body = ProblemDetails),
+        (status = 401, description = "Missing or invalid credentials", body = ProblemDetails),
+        (status = 403, description = "Caller is not an admin", body = ProblemDetails),
+        (status = 409, description = "Email already in use", body = ProblemDetails),
     ),
 )]
 #[instrument(skip(state))]
 pub async fn add_user(
     State(mut state): State<AppState>,
+    _: RequireRole<Admin>,
     ValidatedJson(payload): ValidatedJson<UserRequest>,
 ) -> Result<(StatusCode, Json<User>), AppError> {
     let user = toasty::create!(User {
@@ -36,42 +54,180 @@ pub async fn add_user(
     Ok((StatusCode::CREATED, Json(user)))
 }
 
+/// Fetch a user who has not been deleted.
+pub(crate) async fn find_user(db: &mut toasty::Db, id: i64) -> Result<User, AppError> {
+    User::filter(
+        User::fields()
+            .id()
+            .eq(id)
+            .and(User::fields().deleted_at().is_none()),
+    )
+    .first()
+    .exec(db)
+    .await?
+    .ok_or_else(|| AppError::NotFound("Not found".to_string()))
+}
+
+/// Set the given fields of a user who is not deleted, bumping `updated_at`.
+///
+/// The `deleted_at` check is part of the write rather than a prior read, so
+/// a user deleted after `find_user` is `NotFound` instead of edited.
+/// Timestamps are bound as the RFC 3339 text toasty stores them as.
+async fn write_user(
+    db: &mut toasty::Db,
+    id: i64,
+    name: Option<String>,
+    email: Option<String>,
+) -> Result<(), AppError> {
+    let text = |value: Option<String>| value.map_or(Value::Null, Value::from);
+    let written = toasty::sql::statement(
+        "UPDATE users SET name = coalesce(?1, name), email = coalesce(?2, email), \
+         updated_at = ?3 WHERE id = ?4 AND deleted_at IS NULL",
+    )
+    .bind_typed(text(name), Type::Text)
+    .bind_typed(text(email), Type::Text)
+    .bind(format!("{:.9}", Timestamp::now()))
+    .bind(id)
+    .exec(db)
+    .await?;
+    if written == 0 {
+        return Err(AppError::NotFound("Not found".to_string()));
+    }
+    Ok(())
+}
+
 #[utoipa::path(
     put,
     path = "/user/{id}",
     tag = "Users",
+    description = "Replace a user's name and email. Only the user themself and admins may do this.",
+    security(("api_key" = [])),
     request_body = UserRequest,
     params(
         ("id" = i64, Path, description = "User ID"),
+        ("If-Match" = Option<String>, Header, description = "Only apply the change if this matches the resource's current ETag"),
+        ("Prefer" = Option<String>, Header, description = "Send `return=minimal` to get 204 No Content instead of the updated resource"),
     ),
     responses(
-        (status = 200, description = "User updated"),
-        (status = 400, description = "Validation error", body = String),
+        (status = 200, description = "User updated", body = User, headers(("ETag" = String, description = "Version of the returned resource"))),
+        (status = 204, description = "User updated, body omitted per `Prefer: return=minimal`"),
+        (status = 400, description = "Validation error", body = ProblemDetails),
+        (status = 401, description = "Missing or invalid credentials", body = ProblemDetails),
+        (status = 403, description = "Caller is neither this user nor an admin", body = ProblemDetails),
+        (status = 404, description = "User not found", body = ProblemDetails),
+        (status = 409, description = "Email already in use", body = ProblemDetails),
+        (status = 412, description = "`If-Match` does not match the current ETag", body = ProblemDetails),
     ),
 )]
 #[instrument(skip(state))]
 pub async fn update_user(
     Path(id): Path<i64>,
     State(mut state): State<AppState>,
+    caller: Caller,
+    preconditions: Preconditions,
+    prefer: ReturnPreference,
     ValidatedJson(payload): ValidatedJson<UserRequest>,
-) -> Result<StatusCode, AppError> {
-    toasty::update!(User::filter_by_id(id) {
-        name: payload.name,
-        email: payload.email,
-    })
-    .exec(&mut state.db)
-    .await?;
-    Ok(StatusCode::OK)
+) -> Result<Response, AppError> {
+    caller.ensure_self(id)?;
+    let user = find_user(&mut state.db, id).await?;
+    preconditions.ensure_match(&user.etag())?;
+    write_user(&mut state.db, id, Some(payload.name), Some(payload.email)).await?;
+    let user = find_user(&mut state.db, id).await?;
+    Ok((user.etag(), prefer.respond(user)).into_response())
+}
+
+#[utoipa::path(
+    patch,
+    path = "/user/{id}",
+    tag = "Users",
+    description = "Update only the fields present in the body. Only the user themself and admins may do this. Accepts `application/json` or `application/merge-patch+json`.",
+    security(("api_key" = [])),
+    request_body(content(
+        (UserPatchRequest = "application/json"),
+        (UserPatchRequest = "application/merge-patch+json"),
+    )),
+    params(
+        ("id" = i64, Path, description = "User ID"),
+        ("If-Match" = Option<String>, Header, description = "Only apply the change if this matches the resource's current ETag"),
+        ("Prefer" = Option<String>, Header, description = "Send `return=minimal` to get 204 No Content instead of the updated resource"),
+    ),
+    responses(
+        (status = 200, description = "User updated", body = User, headers(("ETag" = String, description = "Version of the returned resource"))),
+        (status = 204, description = "User updated, body omitted per `Prefer: return=minimal`"),
+        (status = 400, description = "Validation error", body = ProblemDetails),
+        (status = 401, description = "Missing or invalid credentials", body = ProblemDetails),
+        (status = 403, description = "Caller is neither this user nor an admin", body = ProblemDetails),
+        (status = 404, description = "User not found", body = ProblemDetails),
+        (status = 409, description = "Email already in use", body = ProblemDetails),
+        (status = 412, description = "`If-Match` does not match the current ETag", body = ProblemDetails),
+    ),
+)]
+#[instrument(skip(state))]
+pub async fn patch_user(
+    Path(id): Path<i64>,
+    State(mut state): State<AppState>,
+    caller: Caller,
+    preconditions: Preconditions,
+    prefer: ReturnPreference,
+    ValidatedPatch(payload): ValidatedPatch<UserPatchRequest>,
+) -> Result<Response, AppError> {
+    caller.ensure_self(id)?;
+    let user = find_user(&mut state.db, id).await?;
+    preconditions.ensure_match(&user.etag())?;
+    write_user(&mut state.db, id, payload.name, payload.email).await?;
+    let user = find_user(&mut state.db, id).await?;
+    Ok((user.etag(), prefer.respond(user)).into_response())
+}
+
+#[utoipa::path(
+    put,
+    path = "/user/{id}/role",
+    tag = "Users",
+    description = "Change a user's role. Requires the `admin` role.",
+    security(("api_key" = [])),
+    request_body = RoleRequest,
+    params(
+        ("id" = i64, Path, description = "User ID"),
+        ("Prefer" = Option<String>, Header, description = "Send `return=minimal` to get 204 No Content instead of the updated resource"),
+    ),
+    responses(
+        (status = 200, description = "Role updated", body = User),
+        (status = 204, description = "Role updated, body omitted per `Prefer: return=minimal`"),
+        (status = 401, description = "Missing or invalid credentials", body = ProblemDetails),
+        (status = 403, description = "Caller is not an admin", body = ProblemDetails),
+        (status = 404, description = "User not found", body = ProblemDetails),
+    ),
+)]
+#[instrument(skip(state))]
+pub async fn update_user_role(
+    Path(id): Path<i64>,
+    State(mut state): State<AppState>,
+    _: RequireRole<Admin>,
+    prefer: ReturnPreference,
+    ValidatedJson(payload): ValidatedJson<RoleRequest>,
+) -> Result<Response, AppError> {
+    let mut user = find_user(&mut state.db, id).await?;
+    user.update().role(payload.role).exec(&mut state.db).await?;
+    Ok(prefer.respond(user))
 }
 
 #[utoipa::path(
     delete,
     path = "/users",
     tag = "Users",
-    responses((status = 200, description = "All users deleted")),
+    description = "Delete every user. Requires the `admin` role.",
+    security(("api_key" = [])),
+    responses(
+        (status = 200, description = "All users deleted"),
+        (status = 401, description = "Missing or invalid credentials", body = ProblemDetails),
+        (status = 403, description = "Caller is not an admin", body = ProblemDetails),
+    ),
 )]
 #[instrument(skip(state))]
-pub async fn delete_all_users(State(mut state): State<AppState>) -> Result<StatusCode, AppError> {
+pub async fn delete_all_users(
+    State(mut state): State<AppState>,
+    _: RequireRole<Admin>,
+) -> Result<StatusCode, AppError> {
     User::all().delete().exec(&mut state.db).await?;
     Ok(StatusCode::OK)
 }
@@ -82,53 +238,174 @@ pub async fn delete_all_users(State(mut state): State<AppState>) -> Result<Statu
     tag = "Users",
     params(
         ("id" = i64, Path, description = "User ID"),
+        ("If-None-Match" = Option<String>, Header, description = "Respond 304 Not Modified if this matches the resource's current ETag"),
     ),
     responses(
-        (status = 200, description = "User found", body = User),
-        (status = 404, description = "User not found", body = String),
+        (status = 200, description = "User found", body = User, headers(("ETag" = String, description = "Version of the returned resource"))),
+        (status = 304, description = "Not modified since the ETag in `If-None-Match`"),
+        (status = 404, description = "User not found", body = ProblemDetails),
     ),
 )]
 #[instrument(skip(state))]
 pub async fn get_user(
     Path(id): Path<i64>,
     State(mut state): State<AppState>,
-) -> Result<Json<User>, AppError> {
-    let user = User::get_by_id(&mut state.db, id).await?;
-    Ok(Json(user))
+    preconditions: Preconditions,
+) -> Result<Response, AppError> {
+    let user = find_user(&mut state.db, id).await?;
+    let etag = user.etag();
+    if preconditions.not_modified(&etag) {
+        return Ok((etag, StatusCode::NOT_MODIFIED).into_response());
+    }
+    Ok((etag, Json(user)).into_response())
 }
 
 #[utoipa::path(
     get,
     path = "/users",
     tag = "Users",
-    responses((status = 200, description = "List of all users", body = Vec<User>)),
+    params(PaginationParams),
+    responses(
+        (status = 200, description = "Paginated users", body = SerializablePage<User>, headers(("Link" = String, description = "RFC 8288 links to the `next` and `prev` pages"))),
+        (status = 400, description = "Invalid pagination parameters", body = ProblemDetails),
+    ),
 )]
 #[instrument(skip(state))]
-pub async fn get_all_users(State(mut state): State<AppState>) -> Result<Json<Vec<User>>, AppError> {
-    let users = User::all()
-        .order_by(User::fields().id().asc())
-        .exec(&mut state.db)
+pub async fn get_all_users(
+    State(mut state): State<AppState>,
+    Query(params): Query<PaginationParams>,
+    uri: Uri,
+) -> Result<Paginated<User>, AppError> {
+    let page = params
+        .fetch::<UserSort>(User::fields().deleted_at().is_none(), &mut state)
         .await?;
-    Ok(Json(users))
+    Ok(Paginated::new(page, uri))
 }
 
 #[utoipa::path(
     delete,
     path = "/user/{id}",
     tag = "Users",
+    description = "Delete a user and their jokes. They can be restored until the `purge` command removes them for good. Their votes, favorites and reactions stop counting meanwhile; their comments stay until the purge. Only the user themself and admins may do this.",
+    security(("api_key" = [])),
     params(
         ("id" = i64, Path, description = "User ID"),
+        ("If-Match" = Option<String>, Header, description = "Only apply the change if this matches the resource's current ETag"),
     ),
     responses(
-        (status = 200, description = "User deleted"),
-        (status = 404, description = "User not found", body = String),
+        (status = 200, description = "User and their jokes deleted"),
+        (status = 401, description = "Missing or invalid credentials", body = ProblemDetails),
+        (status = 403, description = "Caller is neither this user nor an admin", body = ProblemDetails),
+        (status = 404, description = "User not found", body = ProblemDetails),
+        (status = 412, description = "`If-Match` does not match the current ETag", body = ProblemDetails),
     ),
 )]
 #[instrument(skip(state))]
 pub async fn delete_user(
     Path(id): Path<i64>,
     State(mut state): State<AppState>,
+    caller: Caller,
+    preconditions: Preconditions,
 ) -> Result<StatusCode, AppError> {
-    User::delete_by_id(&mut state.db, id).await?;
+    caller.ensure_self(id)?;
+    let user = find_user(&mut state.db, id).await?;
+    preconditions.ensure_match(&user.etag())?;
+    // Deleting only a user who still is not deleted, in the write itself,
+    // makes one of two concurrent deletes `NotFound` before it touches the
+    // jokes.
+    let now = Timestamp::now();
+    let written = toasty::sql::statement(
+        "UPDATE users SET deleted_at = ?1, updated_at = ?1 WHERE id = ?2 AND deleted_at IS NULL",
+    )
+    .bind(format!("{now:.9}"))
+    .bind(user.id)
+    .exec(&mut state.db)
+    .await?;
+    if written == 0 {
+        return Err(AppError::NotFound("Not found".to_string()));
+    }
+    // The user's jokes go with them, stamped alike so a restore can tell
+    // them from jokes deleted on their own.
+    Joke::filter(
+        Joke::fields()
+            .user_id()
+            .eq(user.id)
+            .and(Joke::fields().deleted_at().is_none()),
+    )
+    .update()
+    .deleted_at(Some(now))
+    .exec(&mut state.db)
+    .await?;
+    refresh_contributions(&mut state.db, user.id).await?;
     Ok(StatusCode::OK)
 }
+
+#[utoipa::path(
+    post,
+    path = "/user/{id}/restore",
+    tag = "Users",
+    description = "Undo the deletion of a user, along with the jokes deleted with them. Restoring a user who is not deleted has no effect. Requires the `admin` role.",
+    security(("api_key" = [])),
+    params(
+        ("id" = i64, Path, description = "User ID"),
+    ),
+    responses(
+        (status = 200, description = "User restored", body = User),
+        (status = 401, description = "Missing or invalid credentials", body = ProblemDetails),
+        (status = 403, description = "Caller is not an admin", body = ProblemDetails),
+        (status = 404, description = "User not found, or already purged", body = ProblemDetails),
+    ),
+)]
+#[instrument(skip(state))]
+pub async fn restore_user(
+    Path(id): Path<i64>,
+    State(mut state): State<AppState>,
+    _: RequireRole<Admin>,
+) -> Result<Json<User>, AppError> {
+    let mut user = User::get_by_id(&mut state.db, id).await?;
+    if let Some(deleted_at) = user.deleted_at {
+        Joke::filter(
+            Joke::fields()
+                .user_id()
+                .eq(user.id)
+                .and(Joke::fields().deleted_at().eq(deleted_at)),
+        )
+        .update()
+        .deleted_at(None)
+        .exec(&mut state.db)
+        .await?;
+        user.update().deleted_at(None).exec(&mut state.db).await?;
+        refresh_contributions(&mut state.db, user.id).await?;
+    }
+    Ok(Json(user))
+}
+
+/// Recount the jokes a user voted on, saved or reacted to, after the user
+/// was deleted or restored.
+async fn refresh_contributions(db: &mut toasty::Db, user_id: i64) -> Result<(), AppError> {
+    let voted = Vote::filter(Vote::fields().user_id().eq(user_id))
+        .exec(db)
+        .await?;
+    for vote in voted {
+        votes::refresh_score(db, vote.joke_id).await?;
+    }
+    let saved = Favorite::filter(Favorite::fields().user_id().eq(user_id))
+        .exec(db)
+        .await?;
+    for favorite in saved {
+        favorites::refresh_favorite_count(db, favorite.joke_id).await?;
+    }
+    let mut reacted: Vec<i64> = Reaction::filter(Reaction::fields().user_id().eq(user_id))
+        .exec(db)
+        .await?
+        .into_iter()
+        .map(|reaction| reaction.joke_id)
+        .collect();
+    // A user can react to a joke more than once, one reaction per kind.
+    reacted.sort_unstable();
+    reacted.dedup();
+    for joke_id in reacted {
+        reactions::refresh_reaction_counts(db, joke_id).await?;
+    }
+    Ok(())
+}
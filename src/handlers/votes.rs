@@ -0,0 +1,137 @@
+use axum::{Json, extract::State, http::StatusCode};
+use tracing::instrument;
+
+use crate::{
+    auth::Caller,
+    error::{AppError, ProblemDetails},
+    handlers::jokes,
+    request::{Path, ValidatedJson, joke_request::VoteRequest},
+    schemas::vote::Vote,
+    state::AppState,
+};
+
+#[utoipa::path(
+    put,
+    path = "/joke/{id}/vote",
+    tag = "Votes",
+    description = "Up- or down-vote a joke as the calling user, replacing any earlier vote.",
+    security(("api_key" = [])),
+    request_body = VoteRequest,
+    params(
+        ("id" = i64, Path, description = "Joke ID"),
+    ),
+    responses(
+        (status = 200, description = "Vote recorded", body = Vote),
+        (status = 400, description = "Validation error", body = ProblemDetails),
+        (status = 401, description = "Missing or invalid credentials", body = ProblemDetails),
+        (status = 403, description = "Caller is not a user", body = ProblemDetails),
+        (status = 404, description = "Joke not found", body = ProblemDetails),
+    ),
+)]
+#[instrument(skip(state))]
+pub async fn put_vote(
+    Path(id): Path<i64>,
+    State(mut state): State<AppState>,
+    caller: Caller,
+    ValidatedJson(payload): ValidatedJson<VoteRequest>,
+) -> Result<Json<Vote>, AppError> {
+    let user_id = caller.require_user()?;
+    let joke = jokes::find_joke(&mut state.db, id).await?;
+    let mut vote = match find_vote(&mut state.db, user_id, joke.id).await? {
+        Some(vote) => vote,
+        None => {
+            let created = toasty::create!(Vote {
+                user_id,
+                joke_id: joke.id,
+                value: payload.value,
+            })
+            .exec(&mut state.db)
+            .await
+            .map_err(AppError::from);
+            match created {
+                Ok(vote) => vote,
+                // Cast concurrently by another request from the same user.
+                Err(AppError::Conflict { .. }) => find_vote(&mut state.db, user_id, joke.id)
+                    .await?
+                    .ok_or_else(|| AppError::Internal("vote vanished".to_string()))?,
+                Err(err) => return Err(err),
+            }
+        }
+    };
+    if vote.value != payload.value {
+        vote.update()
+            .value(payload.value)
+            .exec(&mut state.db)
+            .await?;
+    }
+    refresh_score(&mut state.db, joke.id).await?;
+    Ok(Json(vote))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/joke/{id}/vote",
+    tag = "Votes",
+    description = "Withdraw the calling user's vote on a joke.",
+    security(("api_key" = [])),
+    params(
+        ("id" = i64, Path, description = "Joke ID"),
+    ),
+    responses(
+        (status = 200, description = "Vote withdrawn"),
+        (status = 401, description = "Missing or invalid credentials", body = ProblemDetails),
+        (status = 403, description = "Caller is not a user", body = ProblemDetails),
+        (status = 404, description = "Joke or vote not found", body = ProblemDetails),
+    ),
+)]
+#[instrument(skip(state))]
+pub async fn delete_vote(
+    Path(id): Path<i64>,
+    State(mut state): State<AppState>,
+    caller: Caller,
+) -> Result<StatusCode, AppError> {
+    let user_id = caller.require_user()?;
+    let joke = jokes::find_joke(&mut state.db, id).await?;
+    let vote = find_vote(&mut state.db, user_id, joke.id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("You have not voted on this joke".to_string()))?;
+    vote.delete().exec(&mut state.db).await?;
+    refresh_score(&mut state.db, joke.id).await?;
+    Ok(StatusCode::OK)
+}
+
+async fn find_vote(
+    db: &mut toasty::Db,
+    user_id: i64,
+    joke_id: i64,
+) -> Result<Option<Vote>, toasty::Error> {
+    Vote::filter(
+        Vote::fields()
+            .user_id()
+            .eq(user_id)
+            .and(Vote::fields().joke_id().eq(joke_id)),
+    )
+    .first()
+    .exec(db)
+    .await
+}
+
+/// Recompute a joke's denormalized score from the votes of users who have
+/// not been deleted.
+///
+/// Summing in one statement, rather than adding the change, keeps the score
+/// right under concurrent votes. It also leaves `updated_at` alone, which
+/// tracks edits to the joke itself.
+pub(crate) async fn refresh_score(db: &mut toasty::Db, joke_id: i64) -> Result<(), toasty::Error> {
+    toasty::sql::statement(
+        "UPDATE jokes SET score = \
+         (SELECT coalesce(sum(votes.value), 0) FROM votes \
+         JOIN users ON users.id = votes.user_id \
+         WHERE votes.joke_id = ?1 AND users.deleted_at IS NULL) \
+         WHERE id = ?1",
+    )
+    .bind(joke_id)
+    .exec(db)
+    .await?;
+    Ok(())
+}
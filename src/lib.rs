@@ -1,41 +1,37 @@
+pub mod auth;
+pub mod config;
+pub mod daily;
 pub mod error;
 pub mod handlers;
+pub mod migrate;
 pub mod openapi;
+pub mod purge;
 pub mod request;
 pub mod router;
 pub mod schemas;
+pub mod search;
 pub mod state;
 
 pub use router::create_app;
 
 // Re-exports for convenience and toasty::models! macro discovery.
 pub use request::{joke_request::JokeRequest, user_request::UserRequest};
-pub use schemas::{joke::Joke, user::User};
+pub use schemas::{
+    api_key::ApiKey, comment::Comment, daily_joke::DailyJoke, favorite::Favorite, joke::Joke,
+    joke_revision::JokeRevision, joke_tag::JokeTag, reaction::Reaction,
+    refresh_token::RefreshToken, report::Report, tag::Tag, user::User, vote::Vote,
+};
 pub use state::AppState;
-use toasty::stmt::{Page, Value};
 use utoipa::ToSchema;
 
 #[derive(serde::Serialize, serde::Deserialize, ToSchema)]
 pub struct SerializablePage<T> {
     pub items: Vec<T>,
-    pub cursor: Option<i64>,
-}
-
-fn cursor_to_i64(cursor: Value) -> i64 {
-    cursor
-        .as_record()
-        .expect("cursor is not a record")
-        .first()
-        .expect("cursor record is empty")
-        .to_i64()
-        .expect("cursor record field is not i64")
-}
-
-impl<T> From<Page<T>> for SerializablePage<T> {
-    fn from(page: Page<T>) -> Self {
-        Self {
-            items: page.items,
-            cursor: page.next_cursor.map(cursor_to_i64),
-        }
-    }
+    /// Opaque token; pass as `cursor` to fetch the next page.
+    pub cursor: Option<String>,
+    /// Opaque token; pass as `before` to fetch the previous page.
+    pub prev_cursor: Option<String>,
+    /// Number of items across all pages; only present with `include_total=true`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub total: Option<u64>,
 }
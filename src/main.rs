@@ -1,6 +1,11 @@
-use axum_everyone::{AppState, Joke, User, create_app};
-use clap::Parser;
+use axum_everyone::{
+    ApiKey, AppState, Comment, DailyJoke, Favorite, Joke, JokeRevision, JokeTag, Reaction,
+    RefreshToken, Report, Tag, User, Vote, auth, config::Config, create_app, migrate, purge,
+    search,
+};
+use clap::{Parser, Subcommand};
 use dotenvy::dotenv;
+use jiff::{SignedDuration, Timestamp};
 use tokio::{net::TcpListener, signal};
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
@@ -19,6 +24,30 @@ struct Opts {
     /// Port to listen on
     #[clap(long, default_value = "3000")]
     port: u16,
+    /// Require an API key for read-only routes as well
+    #[clap(long)]
+    private_reads: bool,
+    #[clap(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Issue a new API key and print it once
+    CreateApiKey {
+        /// Label stored alongside the key
+        #[clap(long, default_value = "operator")]
+        name: String,
+        /// Bind the key to a user instead of issuing an operator key
+        #[clap(long)]
+        user_id: Option<i64>,
+    },
+    /// Permanently remove jokes and users deleted before the retention period
+    Purge {
+        /// Days a deleted joke or user can still be restored
+        #[clap(long, default_value = "30")]
+        retention_days: u32,
+    },
 }
 
 #[tokio::main]
@@ -49,16 +78,78 @@ async fn main() -> Result<(), Box<dyn Error>> {
 
     let db_exist = Path::new(&db_file_name).exists();
 
-    let db = toasty::Db::builder()
-        .models(toasty::models!(Joke, User))
+    let mut db = toasty::Db::builder()
+        .models(toasty::models!(
+            ApiKey,
+            Comment,
+            DailyJoke,
+            Favorite,
+            Joke,
+            JokeRevision,
+            JokeTag,
+            Reaction,
+            RefreshToken,
+            Report,
+            Tag,
+            User,
+            Vote
+        ))
         .connect(&db_url)
         .await?;
 
-    if !db_exist {
+    if db_exist {
+        for added in migrate::upgrade(&mut db).await? {
+            tracing::info!("added {added} to {db_file_name}");
+        }
+        let missing = migrate::missing(&mut db).await?;
+        if !missing.is_empty() {
+            return Err(format!(
+                "{db_file_name} was created by an older version and lacks {}, \
+                 which cannot be added automatically; move it aside to start \
+                 with a fresh database",
+                missing.join(", ")
+            )
+            .into());
+        }
+    } else {
         db.push_schema().await?;
     }
+    search::install(&mut db).await?;
 
-    let state = AppState { db };
+    match opts.command {
+        Some(Command::CreateApiKey { name, user_id }) => {
+            if let Some(user_id) = user_id {
+                User::get_by_id(&mut db, user_id).await?;
+            }
+            let (_, key) = auth::issue_api_key(&mut db, &name, user_id).await?;
+            println!("{key}");
+            return Ok(());
+        }
+        Some(Command::Purge { retention_days }) => {
+            let retention = SignedDuration::from_hours(24 * i64::from(retention_days));
+            let before = Timestamp::now().checked_sub(retention)?;
+            let purged = purge::purge(&mut db, before).await?;
+            println!("Purged {} users and {} jokes", purged.users, purged.jokes);
+            return Ok(());
+        }
+        None => {}
+    }
+
+    let mut config = Config {
+        public_reads: !opts.private_reads,
+        ..Config::default()
+    };
+    match env::var("JWT_SECRET") {
+        Ok(secret) => config.jwt_secret = secret,
+        Err(_) => tracing::warn!("JWT_SECRET is not set; tokens will not survive a restart"),
+    }
+    if let Ok(name) = env::var("DAILY_JOKE_TZ") {
+        config.daily_joke_timezone = jiff::tz::TimeZone::get(&name)?;
+    }
+    if let Ok(days) = env::var("DAILY_JOKE_WINDOW_DAYS") {
+        config.daily_joke_window_days = days.parse()?;
+    }
+    let state = AppState::with_config(db, config);
 
     let app = create_app(state);
 
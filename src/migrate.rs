@@ -0,0 +1,144 @@
+//! Startup upgrade of a database file made by an older version.
+//!
+//! `push_schema` only builds a schema from scratch, so an older file lacks the
+//! tables and columns added since. [`upgrade`] adds them with the statements
+//! `push_schema` itself would run, filling required columns of existing rows
+//! from [`DEFAULTS`]. Anything it cannot add is reported by [`missing`], and
+//! the server refuses to start rather than fail on the first query that
+//! touches it.
+
+use toasty::{
+    schema::db::{Index, Table},
+    stmt::{Type, Value},
+};
+use toasty_sql::{Serializer, Statement};
+
+/// SQL defaults for required columns added to tables that may already have
+/// rows, matching the models' `#[default]`s.
+const DEFAULTS: &[(&str, &str, &str)] = &[
+    // `Role::User`
+    ("users", "role", "1"),
+    ("jokes", "score", "0"),
+    ("jokes", "favorite_count", "0"),
+    ("jokes", "reaction_counts", "'{}'"),
+    ("jokes", "hidden", "0"),
+];
+
+/// Create the tables the models expect but the database lacks, and add the
+/// missing columns that are nullable or have a default, with their indices.
+/// Returns the tables and `table.column`s added; running it again finds
+/// nothing to do.
+pub async fn upgrade(db: &mut toasty::Db) -> Result<Vec<String>, toasty::Error> {
+    let handle = db.clone();
+    let schema = &handle.schema().db;
+    let serializer = Serializer::sqlite(schema);
+    let mut added = Vec::new();
+    for table in &schema.tables {
+        let present = columns(db, &table.name).await?;
+        let mut statements = Vec::new();
+        if present.is_empty() {
+            let create = Statement::create_table(table, handle.capability());
+            statements.push(serializer.serialize(&create));
+            added.push(table.name.clone());
+        } else {
+            for column in table.columns.iter().filter(|c| !present.contains(&c.name)) {
+                let default = default(&table.name, &column.name);
+                if !column.nullable && default.is_none() {
+                    continue;
+                }
+                let add = Statement::add_column(column, handle.capability());
+                let mut sql = serializer.serialize(&add);
+                if let Some(default) = default {
+                    sql = format!("{} DEFAULT {default}", sql.trim_end_matches(';'));
+                }
+                statements.push(sql);
+                added.push(format!("{}.{}", table.name, column.name));
+            }
+        }
+        for sql in statements {
+            toasty::sql::statement(sql).exec(db).await?;
+        }
+
+        let present = columns(db, &table.name).await?;
+        let indexed = indices(db, &table.name).await?;
+        let wanted = table.indices.iter().filter(|index| {
+            !index.primary_key && !indexed.contains(&index.name) && covers(table, index, &present)
+        });
+        for index in wanted {
+            toasty::sql::statement(serializer.serialize(&Statement::create_index(index)))
+                .exec(db)
+                .await?;
+        }
+    }
+    Ok(added)
+}
+
+/// Tables, and `table.column`s of existing tables, that the models expect
+/// but the database lacks.
+pub async fn missing(db: &mut toasty::Db) -> Result<Vec<String>, toasty::Error> {
+    let expected: Vec<(String, Vec<String>)> = db
+        .schema()
+        .db
+        .tables
+        .iter()
+        .map(|table| {
+            let columns = table.columns.iter().map(|c| c.name.clone()).collect();
+            (table.name.clone(), columns)
+        })
+        .collect();
+    let mut missing = Vec::new();
+    for (table, columns) in expected {
+        let present = self::columns(db, &table).await?;
+        if present.is_empty() {
+            missing.push(table);
+            continue;
+        }
+        missing.extend(
+            columns
+                .into_iter()
+                .filter(|column| !present.contains(column))
+                .map(|column| format!("{table}.{column}")),
+        );
+    }
+    Ok(missing)
+}
+
+fn default(table: &str, column: &str) -> Option<&'static str> {
+    DEFAULTS
+        .iter()
+        .find(|(t, c, _)| *t == table && *c == column)
+        .map(|(_, _, default)| *default)
+}
+
+/// Whether every column `index` is on exists in the database.
+fn covers(table: &Table, index: &Index, present: &[String]) -> bool {
+    index
+        .columns
+        .iter()
+        .all(|c| present.contains(&table.columns[c.column.index].name))
+}
+
+/// The columns `table` has in the database; empty if there is no such table.
+async fn columns(db: &mut toasty::Db, table: &str) -> Result<Vec<String>, toasty::Error> {
+    names(db, "SELECT name FROM pragma_table_info(?1)", table).await
+}
+
+/// The names of the indices on `table` in the database.
+async fn indices(db: &mut toasty::Db, table: &str) -> Result<Vec<String>, toasty::Error> {
+    names(db, "SELECT name FROM pragma_index_list(?1)", table).await
+}
+
+async fn names(db: &mut toasty::Db, sql: &str, table: &str) -> Result<Vec<String>, toasty::Error> {
+    let rows = toasty::sql::query(sql)
+        .bind(table.to_string())
+        .column_types([Type::String])
+        .exec(db)
+        .await?;
+    Ok(rows
+        .iter()
+        .filter_map(|row| match row.as_record().map(|r| r.as_slice()) {
+            Some([Value::String(name)]) => Some(name.clone()),
+            _ => None,
+        })
+        .collect())
+}
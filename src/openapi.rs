@@ -1,10 +1,30 @@
-use utoipa::OpenApi;
+use utoipa::{
+    Modify, OpenApi,
+    openapi::{
+        ContentBuilder, Ref, RefOr, ResponseBuilder,
+        security::{HttpAuthScheme, HttpBuilder, SecurityScheme},
+    },
+};
 
 use crate::SerializablePage;
-use crate::request::joke_request::{JokeRequest, PaginationParams};
-use crate::request::user_request::UserRequest;
+use crate::auth::jwt::TokenPair;
+use crate::error::{PROBLEM_JSON, ProblemDetails};
+use crate::request::auth_request::{LoginRequest, RefreshRequest, RegisterRequest};
+use crate::request::comment_request::{CommentRequest, CommentUpdateRequest};
+use crate::request::joke_request::{JokePatchRequest, JokeRequest, VoteRequest};
+use crate::request::pagination::{PaginationParams, SortOrder};
+use crate::request::report_request::{ReportRequest, ResolveReportRequest};
+use crate::request::user_request::{RoleRequest, UserPatchRequest, UserRequest};
+use crate::schemas::comment::Comment;
+use crate::schemas::favorite::Favorite;
 use crate::schemas::joke::Joke;
-use crate::schemas::user::User;
+use crate::schemas::joke_revision::JokeRevision;
+use crate::schemas::reaction::{Reaction, ReactionKind};
+use crate::schemas::report::{Report, ReportReason, ReportStatus};
+use crate::schemas::tag::{Tag, TagUsage};
+use crate::schemas::user::{Role, User};
+use crate::schemas::vote::Vote;
+use crate::search::SearchHit;
 
 #[derive(OpenApi)]
 #[openapi(
@@ -16,17 +36,126 @@ use crate::schemas::user::User;
     components(
         schemas(
             User,
+            Role,
             Joke,
             UserRequest,
+            UserPatchRequest,
+            RoleRequest,
             JokeRequest,
+            JokePatchRequest,
+            Vote,
+            VoteRequest,
+            Favorite,
+            Reaction,
+            ReactionKind,
+            Tag,
+            TagUsage,
             PaginationParams,
+            SortOrder,
             SerializablePage<Joke>,
+            SerializablePage<User>,
+            Comment,
+            CommentRequest,
+            CommentUpdateRequest,
+            SerializablePage<Comment>,
+            JokeRevision,
+            SerializablePage<JokeRevision>,
+            Report,
+            ReportReason,
+            ReportStatus,
+            ReportRequest,
+            ResolveReportRequest,
+            SerializablePage<Report>,
+            SearchHit,
+            SerializablePage<SearchHit>,
+            RegisterRequest,
+            LoginRequest,
+            RefreshRequest,
+            TokenPair,
+            ProblemDetails,
         )
     ),
+    modifiers(&SecurityAddon),
     tags(
         (name = "Health", description = "Health check endpoints"),
+        (name = "Auth", description = "Registration, login and token refresh"),
         (name = "Users", description = "User management endpoints"),
         (name = "Jokes", description = "Joke management endpoints"),
+        (name = "Votes", description = "Voting on jokes"),
+        (name = "Tags", description = "Classifying jokes with tags"),
+        (name = "Comments", description = "Discussion threads on jokes"),
+        (name = "Favorites", description = "Jokes users have saved"),
+        (name = "Reactions", description = "Emoji reactions to jokes"),
+        (name = "Reports", description = "Reporting jokes and the moderation queue"),
+        (name = "Revisions", description = "History of joke content"),
     ),
 )]
 pub struct ApiDoc;
+
+/// Registers the `api_key` bearer scheme referenced by mutating operations.
+/// It accepts either an API key or a JWT access token from `/auth/login`.
+struct SecurityAddon;
+
+impl Modify for SecurityAddon {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            "api_key",
+            SecurityScheme::Http(
+                HttpBuilder::new()
+                    .scheme(HttpAuthScheme::Bearer)
+                    .description(Some(
+                        "API key or JWT access token sent as `Authorization: Bearer <token>`",
+                    ))
+                    .build(),
+            ),
+        );
+    }
+}
+
+/// Serve every documented `ProblemDetails` body as `application/problem+json`
+/// and add a 500 problem response to each operation.
+///
+/// Runs on the merged document, since route paths are only known after
+/// `OpenApiRouter::split_for_parts`.
+pub fn document_problem_responses(api: &mut utoipa::openapi::OpenApi) {
+    let problem_ref = Ref::from_schema_name("ProblemDetails");
+    for item in api.paths.paths.values_mut() {
+        let operations = [
+            &mut item.get,
+            &mut item.put,
+            &mut item.post,
+            &mut item.delete,
+            &mut item.patch,
+            &mut item.head,
+        ];
+        for operation in operations.into_iter().flatten() {
+            let responses = &mut operation.responses.responses;
+            for response in responses.values_mut() {
+                let RefOr::T(response) = response else {
+                    continue;
+                };
+                let is_problem = response.content.get("application/json").is_some_and(
+                    |content| matches!(&content.schema, Some(RefOr::Ref(r)) if *r == problem_ref),
+                );
+                if is_problem
+                    && let Some(content) = response.content.shift_remove("application/json")
+                {
+                    response.content.insert(PROBLEM_JSON.to_string(), content);
+                }
+            }
+            responses.entry("500".to_string()).or_insert_with(|| {
+                ResponseBuilder::new()
+                    .description("Internal server error")
+                    .content(
+                        PROBLEM_JSON,
+                        ContentBuilder::new()
+                            .schema(Some(problem_ref.clone()))
+                            .build(),
+                    )
+                    .build()
+                    .into()
+            });
+        }
+    }
+}
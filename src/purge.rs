@@ -0,0 +1,62 @@
+//! Permanent removal of soft-deleted jokes and users.
+//!
+//! Deleting through the API only stamps `deleted_at`, so mistakes can be
+//! undone with a restore. Rows deleted longer ago than the retention period
+//! are removed for good by the `purge` command.
+
+use jiff::Timestamp;
+
+use crate::{
+    error::AppError,
+    handlers::comments,
+    schemas::{comment::Comment, joke::Joke, user::User},
+};
+
+/// How many rows a purge removed.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Purged {
+    pub users: u64,
+    pub jokes: u64,
+}
+
+/// Hard-delete every user and joke deleted before `before`.
+///
+/// A purged user's jokes and comments go with them.
+pub async fn purge(db: &mut toasty::Db, before: Timestamp) -> Result<Purged, AppError> {
+    let mut purged = Purged::default();
+
+    let users = User::filter(User::fields().deleted_at().lt(before))
+        .exec(db)
+        .await?;
+    for user in users {
+        purged.jokes += Joke::filter(Joke::fields().user_id().eq(user.id))
+            .count()
+            .exec(db)
+            .await?;
+        delete_user(db, user).await?;
+        purged.users += 1;
+    }
+
+    let jokes = Joke::filter(Joke::fields().deleted_at().lt(before))
+        .exec(db)
+        .await?;
+    for joke in jokes {
+        joke.delete().exec(db).await?;
+        purged.jokes += 1;
+    }
+    Ok(purged)
+}
+
+async fn delete_user(db: &mut toasty::Db, user: User) -> Result<(), AppError> {
+    // Their votes, favorites and reactions stopped counting when they were
+    // deleted, but whole threads under their comments must go by hand.
+    let commented: Vec<i64> = Comment::filter(Comment::fields().user_id().eq(user.id))
+        .exec(db)
+        .await?
+        .into_iter()
+        .map(|comment| comment.id)
+        .collect();
+    comments::delete_threads(db, commented).await?;
+    user.delete().exec(db).await?;
+    Ok(())
+}
@@ -0,0 +1,32 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use validator::Validate;
+
+#[derive(Serialize, Deserialize, Validate, ToSchema)]
+pub struct RegisterRequest {
+    #[validate(length(
+        min = 1,
+        max = 255,
+        message = "User name must be between 1 and 255 characters"
+    ))]
+    pub name: String,
+    #[validate(email(message = "User email must be a valid email address"))]
+    pub email: String,
+    #[validate(length(
+        min = 8,
+        max = 128,
+        message = "Password must be between 8 and 128 characters"
+    ))]
+    pub password: String,
+}
+
+#[derive(Serialize, Deserialize, Validate, ToSchema)]
+pub struct LoginRequest {
+    pub email: String,
+    pub password: String,
+}
+
+#[derive(Serialize, Deserialize, Validate, ToSchema)]
+pub struct RefreshRequest {
+    pub refresh_token: String,
+}
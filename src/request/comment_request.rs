@@ -0,0 +1,96 @@
+use serde::{Deserialize, Serialize};
+use toasty::stmt::{Expr, OrderBy, Value};
+use utoipa::{IntoParams, ToSchema};
+use validator::Validate;
+
+use crate::{
+    request::pagination::{Comparison, SortKey, SortOrder},
+    schemas::comment::Comment,
+};
+
+#[derive(Debug, Serialize, Deserialize, Validate, ToSchema)]
+pub struct CommentRequest {
+    #[validate(length(
+        min = 1,
+        max = 2000,
+        message = "Comment content must be between 1 and 2000 characters"
+    ))]
+    pub content: String,
+    /// Reply to this comment, which must be on the same joke.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent_id: Option<i64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate, ToSchema)]
+pub struct CommentUpdateRequest {
+    #[validate(length(
+        min = 1,
+        max = 2000,
+        message = "Comment content must be between 1 and 2000 characters"
+    ))]
+    pub content: String,
+}
+
+/// Which level of a thread to list.
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct CommentFilter {
+    /// List the replies to this comment instead of the top-level comments.
+    pub parent_id: Option<i64>,
+}
+
+impl CommentFilter {
+    pub fn to_expr(&self, joke_id: i64) -> Expr<bool> {
+        let level = match self.parent_id {
+            Some(parent_id) => Comment::fields().parent_id().eq(parent_id),
+            None => Comment::fields().parent_id().is_none(),
+        };
+        Comment::fields().joke_id().eq(joke_id).and(level)
+    }
+}
+
+/// Fields comment listings can be sorted by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CommentSort {
+    Id,
+    CreatedAt,
+}
+
+impl SortKey for CommentSort {
+    type Model = Comment;
+
+    const ID: Self = Self::Id;
+    const NAMES: &'static [&'static str] = &["id", "created_at"];
+
+    fn parse(name: &str) -> Option<Self> {
+        match name {
+            "id" => Some(Self::Id),
+            "created_at" => Some(Self::CreatedAt),
+            _ => None,
+        }
+    }
+
+    fn order_by(self, order: SortOrder) -> OrderBy {
+        match self {
+            Self::Id => order.by(Comment::fields().id()),
+            Self::CreatedAt => order.by(Comment::fields().created_at()),
+        }
+    }
+
+    fn value(self, comment: &Comment) -> Value {
+        match self {
+            Self::Id => Value::I64(comment.id),
+            Self::CreatedAt => Value::Timestamp(comment.created_at),
+        }
+    }
+
+    fn compare(self, op: Comparison, value: Value) -> Option<Expr<bool>> {
+        match (self, value) {
+            (Self::Id, Value::I64(id)) => Some(op.apply(Comment::fields().id(), id)),
+            (Self::CreatedAt, Value::Timestamp(at)) => {
+                Some(op.apply(Comment::fields().created_at(), at))
+            }
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,142 @@
+use std::{convert::Infallible, fmt};
+
+use axum::{
+    extract::FromRequestParts,
+    http::{HeaderMap, HeaderValue, header, request::Parts},
+    response::{IntoResponseParts, ResponseParts},
+};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    error::AppError,
+    schemas::{joke::Joke, user::User},
+};
+
+/// A strong entity tag, derived from a record's id and last update time.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ETag(String);
+
+impl ETag {
+    pub fn new(id: i64, updated_at: jiff::Timestamp) -> Self {
+        Self(format!("\"{id:x}-{:x}\"", updated_at.as_nanosecond()))
+    }
+
+    /// Also vary with `counter`, for denormalized values that change without
+    /// touching `updated_at`.
+    pub fn with_counter(self, counter: i64) -> Self {
+        let tag = self.0.trim_end_matches('"');
+        Self(format!("{tag}-{counter:x}\""))
+    }
+
+    /// Strong comparison (RFC 9110 §8.8.3.2), used by `If-Match`.
+    fn strong_eq(&self, tag: &str) -> bool {
+        self.0 == tag
+    }
+
+    /// Weak comparison, used by `If-None-Match`.
+    fn weak_eq(&self, tag: &str) -> bool {
+        self.0 == tag.strip_prefix("W/").unwrap_or(tag)
+    }
+}
+
+impl fmt::Display for ETag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl IntoResponseParts for ETag {
+    type Error = Infallible;
+
+    fn into_response_parts(self, mut res: ResponseParts) -> Result<ResponseParts, Self::Error> {
+        if let Ok(value) = HeaderValue::from_str(&self.0) {
+            res.headers_mut().insert(header::ETAG, value);
+        }
+        Ok(res)
+    }
+}
+
+/// Records that can be fetched and updated conditionally.
+pub trait Tagged {
+    fn etag(&self) -> ETag;
+}
+
+impl Tagged for Joke {
+    fn etag(&self) -> ETag {
+        // Digest the counts rather than `Hash` them, so tags handed out
+        // survive a rebuild with another Rust release.
+        let reactions = self
+            .reaction_counts
+            .iter()
+            .map(|(kind, count)| format!("{kind}:{count}"))
+            .collect::<Vec<_>>()
+            .join(",");
+        let digest = Sha256::digest(reactions.as_bytes());
+        let reactions = i64::from_be_bytes(digest[..8].try_into().expect("digest is 32 bytes"));
+        ETag::new(self.id, self.updated_at)
+            .with_counter(self.score)
+            .with_counter(self.favorite_count)
+            .with_counter(reactions)
+    }
+}
+
+impl Tagged for User {
+    fn etag(&self) -> ETag {
+        ETag::new(self.id, self.updated_at)
+    }
+}
+
+/// The `If-Match` and `If-None-Match` request headers.
+#[derive(Debug, Default)]
+pub struct Preconditions {
+    if_match: Option<Vec<String>>,
+    if_none_match: Option<Vec<String>>,
+}
+
+impl Preconditions {
+    /// Whether a GET can be answered with `304 Not Modified`.
+    pub fn not_modified(&self, etag: &ETag) -> bool {
+        self.if_none_match
+            .as_ref()
+            .is_some_and(|tags| tags.iter().any(|tag| tag == "*" || etag.weak_eq(tag)))
+    }
+
+    /// Fail with `412 Precondition Failed` if the client's `If-Match` does
+    /// not name the current version of the resource.
+    pub fn ensure_match(&self, etag: &ETag) -> Result<(), AppError> {
+        match &self.if_match {
+            Some(tags) if !tags.iter().any(|tag| tag == "*" || etag.strong_eq(tag)) => {
+                Err(AppError::PreconditionFailed)
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+impl<S> FromRequestParts<S> for Preconditions
+where
+    S: Send + Sync,
+{
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(Self {
+            if_match: entity_tags(&parts.headers, header::IF_MATCH),
+            if_none_match: entity_tags(&parts.headers, header::IF_NONE_MATCH),
+        })
+    }
+}
+
+/// Collect the comma-separated entity tags of every `name` header, or `None`
+/// if the header is absent.
+fn entity_tags(headers: &HeaderMap, name: header::HeaderName) -> Option<Vec<String>> {
+    let mut values = headers.get_all(name).iter().peekable();
+    values.peek()?;
+    let tags = values
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(|tag| tag.trim().to_string())
+        .filter(|tag| !tag.is_empty())
+        .collect();
+    Some(tags)
+}
@@ -1,6 +1,12 @@
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
+use toasty::stmt::{Expr, OrderBy, Value};
 use utoipa::{IntoParams, ToSchema};
-use validator::Validate;
+use validator::{Validate, ValidationError};
+
+use crate::{
+    request::pagination::{Comparison, SortKey, SortOrder},
+    schemas::joke::Joke,
+};
 
 #[derive(Debug, Serialize, Deserialize, Validate, ToSchema)]
 pub struct JokeRequest {
@@ -10,10 +16,210 @@ pub struct JokeRequest {
         message = "Joke content must be between 1 and 1000 characters"
     ))]
     pub content: String,
+    /// Replaces the joke's tags; omitted leaves them unchanged.
+    #[validate(
+        length(max = 10, message = "A joke can have at most 10 tags"),
+        custom(function = "validate_tags")
+    )]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tags: Option<Vec<String>>,
+}
+
+/// Partial update of a joke; omitted fields are left unchanged, as is a
+/// `null` content.
+#[derive(Debug, Default, Serialize, Deserialize, Validate, ToSchema)]
+pub struct JokePatchRequest {
+    #[validate(length(
+        min = 1,
+        max = 1000,
+        message = "Joke content must be between 1 and 1000 characters"
+    ))]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content: Option<String>,
+    /// Replaces the joke's tags; omitted leaves them unchanged and `null`
+    /// removes them.
+    #[validate(
+        length(max = 10, message = "A joke can have at most 10 tags"),
+        custom(function = "validate_tags")
+    )]
+    #[serde(
+        default,
+        deserialize_with = "present",
+        skip_serializing_if = "Option::is_none"
+    )]
+    #[schema(value_type = Option<Vec<String>>, nullable)]
+    pub tags: Option<Option<Vec<String>>>,
+}
+
+/// Wrap a field that is present, even as `null`, in `Some`, so it can be told
+/// apart from an omitted one.
+fn present<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    T::deserialize(deserializer).map(Some)
 }
 
-#[derive(Debug, Default, Serialize, Deserialize, ToSchema, IntoParams)]
-pub struct PaginationParams {
-    pub cursor: Option<i64>,
-    pub page_size: Option<usize>,
+/// Full-text search terms.
+#[derive(Debug, Deserialize, Validate, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct SearchQuery {
+    /// Words to search joke content for; every word must match.
+    #[validate(length(
+        min = 1,
+        max = 200,
+        message = "Search query must be between 1 and 200 characters"
+    ))]
+    pub q: String,
+}
+
+pub const MAX_TAG_LENGTH: usize = 30;
+
+/// Tags are lowercase ASCII letters, digits and inner hyphens, e.g. `knock-knock`.
+fn validate_tags(tags: &[String]) -> Result<(), ValidationError> {
+    let valid = |tag: &String| {
+        (1..=MAX_TAG_LENGTH).contains(&tag.len())
+            && !tag.starts_with('-')
+            && !tag.ends_with('-')
+            && tag
+                .bytes()
+                .all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'-')
+    };
+    if tags.iter().all(valid) {
+        Ok(())
+    } else {
+        Err(ValidationError::new("tag")
+            .with_message("Tags must be 1 to 30 lowercase letters, digits or inner hyphens".into()))
+    }
+}
+
+/// A vote on a joke: `1` to up-vote, `-1` to down-vote.
+#[derive(Debug, Serialize, Deserialize, Validate, ToSchema)]
+pub struct VoteRequest {
+    #[validate(custom(function = "validate_vote"))]
+    pub value: i64,
+}
+
+fn validate_vote(value: i64) -> Result<(), ValidationError> {
+    if value == 1 || value == -1 {
+        Ok(())
+    } else {
+        Err(ValidationError::new("vote").with_message("Vote must be 1 or -1".into()))
+    }
+}
+
+/// Jokes neither hidden by a moderator nor deleted, the only ones joke
+/// listings show.
+pub fn visible() -> Expr<bool> {
+    Joke::fields()
+        .hidden()
+        .eq(false)
+        .and(Joke::fields().deleted_at().is_none())
+}
+
+/// Filters for joke listings. Hidden and deleted jokes are always left out.
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct JokeFilter {
+    /// Only jokes by this user.
+    pub user_id: Option<i64>,
+    /// Only jokes created at or after this RFC 3339 timestamp.
+    #[param(value_type = Option<String>, format = DateTime)]
+    pub created_after: Option<jiff::Timestamp>,
+    /// Only jokes created before this RFC 3339 timestamp.
+    #[param(value_type = Option<String>, format = DateTime)]
+    pub created_before: Option<jiff::Timestamp>,
+    /// Only jokes whose content contains this text, ignoring ASCII case.
+    pub contains: Option<String>,
+}
+
+impl JokeFilter {
+    pub fn to_expr(&self) -> Expr<bool> {
+        let mut conditions = vec![visible()];
+        if let Some(user_id) = self.user_id {
+            conditions.push(Joke::fields().user_id().eq(user_id));
+        }
+        if let Some(after) = self.created_after {
+            conditions.push(Joke::fields().created_at().ge(after));
+        }
+        if let Some(before) = self.created_before {
+            conditions.push(Joke::fields().created_at().lt(before));
+        }
+        if let Some(text) = &self.contains {
+            let escaped = text
+                .replace('\\', "\\\\")
+                .replace('%', "\\%")
+                .replace('_', "\\_");
+            conditions.push(
+                Joke::fields()
+                    .content()
+                    .like_with_escape(format!("%{escaped}%"), '\\'),
+            );
+        }
+        Expr::and_all(conditions)
+    }
+}
+
+/// Fields joke listings can be sorted by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JokeSort {
+    Id,
+    CreatedAt,
+    UpdatedAt,
+    Score,
+}
+
+impl SortKey for JokeSort {
+    type Model = Joke;
+
+    const ID: Self = Self::Id;
+    const NAMES: &'static [&'static str] = &["id", "created_at", "updated_at", "score", "top"];
+
+    fn parse(name: &str) -> Option<Self> {
+        match name {
+            "id" => Some(Self::Id),
+            "created_at" => Some(Self::CreatedAt),
+            "updated_at" => Some(Self::UpdatedAt),
+            "score" => Some(Self::Score),
+            _ => None,
+        }
+    }
+
+    fn alias(name: &str) -> Option<&'static str> {
+        // Leaderboards: highest score first.
+        (name == "top").then_some("-score")
+    }
+
+    fn order_by(self, order: SortOrder) -> OrderBy {
+        match self {
+            Self::Id => order.by(Joke::fields().id()),
+            Self::CreatedAt => order.by(Joke::fields().created_at()),
+            Self::UpdatedAt => order.by(Joke::fields().updated_at()),
+            Self::Score => order.by(Joke::fields().score()),
+        }
+    }
+
+    fn value(self, joke: &Joke) -> Value {
+        match self {
+            Self::Id => Value::I64(joke.id),
+            Self::CreatedAt => Value::Timestamp(joke.created_at),
+            Self::UpdatedAt => Value::Timestamp(joke.updated_at),
+            Self::Score => Value::I64(joke.score),
+        }
+    }
+
+    fn compare(self, op: Comparison, value: Value) -> Option<Expr<bool>> {
+        match (self, value) {
+            (Self::Id, Value::I64(id)) => Some(op.apply(Joke::fields().id(), id)),
+            (Self::CreatedAt, Value::Timestamp(at)) => {
+                Some(op.apply(Joke::fields().created_at(), at))
+            }
+            (Self::UpdatedAt, Value::Timestamp(at)) => {
+                Some(op.apply(Joke::fields().updated_at(), at))
+            }
+            (Self::Score, Value::I64(score)) => Some(op.apply(Joke::fields().score(), score)),
+            _ => None,
+        }
+    }
 }
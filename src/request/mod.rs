@@ -1,15 +1,36 @@
+pub mod auth_request;
+pub mod comment_request;
+pub mod conditional;
+pub mod cursor;
 pub mod joke_request;
+pub mod pagination;
+pub mod prefer;
+pub mod report_request;
+pub mod revision_request;
 pub mod user_request;
 
 use axum::{
     Json,
-    extract::{FromRequest, Request, rejection::JsonRejection},
+    extract::{FromRequest, FromRequestParts, Request, rejection::JsonRejection},
+    http::{HeaderValue, header},
 };
 use serde::de::DeserializeOwned;
 use validator::Validate;
 
 use crate::error::AppError;
 
+pub const MERGE_PATCH_JSON: &str = "application/merge-patch+json";
+
+/// [`axum::extract::Path`], rejecting malformed parameters as problem details.
+#[derive(Debug, FromRequestParts)]
+#[from_request(via(axum::extract::Path), rejection(AppError))]
+pub struct Path<T>(pub T);
+
+/// [`axum::extract::Query`], rejecting malformed parameters as problem details.
+#[derive(Debug, FromRequestParts)]
+#[from_request(via(axum::extract::Query), rejection(AppError))]
+pub struct Query<T>(pub T);
+
 #[derive(Debug)]
 pub struct ValidatedJson<T>(pub T);
 
@@ -27,3 +48,35 @@ where
         Ok(Self(payload))
     }
 }
+
+/// Like [`ValidatedJson`], for partial updates: `T` has all-optional fields and
+/// only the ones present in the body are validated. Besides `application/json`,
+/// the body may be sent as an RFC 7396 `application/merge-patch+json` document.
+#[derive(Debug)]
+pub struct ValidatedPatch<T>(pub T);
+
+impl<T, S> FromRequest<S> for ValidatedPatch<T>
+where
+    T: Validate + DeserializeOwned,
+    S: Send + Sync,
+    Json<T>: FromRequest<S, Rejection = JsonRejection>,
+{
+    type Rejection = AppError;
+
+    async fn from_request(mut req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let is_merge_patch = req
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.split(';').next())
+            .is_some_and(|mime| mime.trim().eq_ignore_ascii_case(MERGE_PATCH_JSON));
+        if is_merge_patch {
+            req.headers_mut().insert(
+                header::CONTENT_TYPE,
+                HeaderValue::from_static("application/json"),
+            );
+        }
+        let ValidatedJson(payload) = ValidatedJson::<T>::from_request(req, state).await?;
+        Ok(Self(payload))
+    }
+}
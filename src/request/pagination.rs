@@ -0,0 +1,386 @@
+use axum::{
+    Json,
+    http::{HeaderValue, Uri, header},
+    response::{IntoResponse, Response},
+};
+use serde::{Deserialize, Serialize};
+use toasty::{
+    schema::Model,
+    stmt::{Expr, IntoExpr, List, OrderBy, Path, Query, Value},
+};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::{SerializablePage, error::AppError, request::cursor, state::AppState};
+
+pub const DEFAULT_PAGE_SIZE: usize = 10;
+pub const MAX_PAGE_SIZE: usize = 100;
+
+/// Direction of a paginated listing.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum SortOrder {
+    #[default]
+    Asc,
+    Desc,
+}
+
+impl SortOrder {
+    pub fn by<T, U>(self, path: Path<T, U>) -> OrderBy {
+        match self {
+            Self::Asc => path.asc().into(),
+            Self::Desc => path.desc().into(),
+        }
+    }
+
+    fn reverse(self) -> Self {
+        match self {
+            Self::Asc => Self::Desc,
+            Self::Desc => Self::Asc,
+        }
+    }
+}
+
+/// A comparison between a sort field and a cursor value.
+#[derive(Debug, Clone, Copy)]
+pub enum Comparison {
+    Eq,
+    Lt,
+    Gt,
+}
+
+impl Comparison {
+    pub fn apply<T, U>(self, path: Path<T, U>, value: impl IntoExpr<U>) -> Expr<bool> {
+        match self {
+            Self::Eq => path.eq(value),
+            Self::Lt => path.lt(value),
+            Self::Gt => path.gt(value),
+        }
+    }
+}
+
+/// The whitelist of fields a listing of `Model` can be sorted by.
+pub trait SortKey: Copy + PartialEq + 'static {
+    type Model: Model;
+
+    /// The primary key, appended to every sort so pages have a total order.
+    const ID: Self;
+
+    /// Accepted names, as used in the `sort` parameter.
+    const NAMES: &'static [&'static str];
+
+    fn parse(name: &str) -> Option<Self>;
+
+    /// Shorthand for a signed field in `sort`, e.g. `top` for `-score`.
+    fn alias(_name: &str) -> Option<&'static str> {
+        None
+    }
+
+    fn order_by(self, order: SortOrder) -> OrderBy;
+
+    /// This field's value in `item`, as stored in cursors.
+    fn value(self, item: &Self::Model) -> Value;
+
+    /// `field <op> value`, or `None` if `value` has the wrong type.
+    fn compare(self, op: Comparison, value: Value) -> Option<Expr<bool>>;
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, ToSchema, IntoParams)]
+pub struct PaginationParams {
+    /// Return the page after this cursor (a page's `cursor`).
+    pub cursor: Option<String>,
+    /// Return the page before this cursor (a page's `prev_cursor`).
+    pub before: Option<String>,
+    /// Items per page; defaults to 10 and is capped at 100.
+    pub page_size: Option<usize>,
+    /// Sort by id, ascending (oldest first) by default.
+    pub order: Option<SortOrder>,
+    /// Comma-separated fields to sort by, each optionally prefixed with `-`
+    /// for descending order, e.g. `-created_at,id`. Supersedes `order`.
+    /// Jokes also accept `top`, short for `-score`.
+    pub sort: Option<String>,
+    /// Also count the items across all pages.
+    #[serde(default)]
+    pub include_total: bool,
+}
+
+impl PaginationParams {
+    /// The requested page size, clamped to `1..=MAX_PAGE_SIZE`.
+    pub fn page_size(&self) -> usize {
+        self.page_size
+            .unwrap_or(DEFAULT_PAGE_SIZE)
+            .clamp(1, MAX_PAGE_SIZE)
+    }
+
+    /// Parse `sort` against the whitelist in `K`, always ending with the id.
+    pub fn sort<K: SortKey>(&self) -> Result<Vec<(K, SortOrder)>, AppError> {
+        sort_keys(self.sort.as_deref(), self.order)
+    }
+
+    /// Fetch the requested page of items matching `filter`.
+    ///
+    /// Pages are found by keyset: the cursor holds the boundary row's value
+    /// for every sort field, and the next page is every row that sorts after
+    /// it.
+    pub async fn fetch<K: SortKey>(
+        &self,
+        filter: impl IntoExpr<bool>,
+        state: &mut AppState,
+    ) -> Result<SerializablePage<K::Model>, AppError> {
+        let keys = self.sort::<K>()?;
+        let filter = filter.into_expr();
+        let secret = state.config.jwt_secret.clone();
+        let (seek, backward) = match (&self.cursor, &self.before) {
+            (Some(_), Some(_)) => {
+                return Err(AppError::BadRequest(
+                    "`cursor` and `before` cannot be combined".to_string(),
+                ));
+            }
+            (Some(after), None) => (Some(cursor::decode(&secret, after)?), false),
+            (None, Some(before)) => (Some(cursor::decode(&secret, before)?), true),
+            (None, None) => (None, false),
+        };
+        let seeking = seek.is_some();
+
+        let mut query = Query::<List<K::Model>>::all().filter(filter.clone());
+        if let Some(seek) = seek {
+            query = query.filter(keyset(&keys, seek, backward)?);
+        }
+        // Fetch one extra row to learn whether there is another page.
+        let page_size = self.page_size();
+        let mut items = sorted(query, &keys, backward)
+            .limit(page_size + 1)
+            .exec(&mut state.db)
+            .await?;
+        let more = items.len() > page_size;
+        items.truncate(page_size);
+        if backward {
+            items.reverse();
+        }
+
+        let (next, prev) = if backward {
+            (items.last(), items.first().filter(|_| more))
+        } else {
+            (
+                items.last().filter(|_| more),
+                items.first().filter(|_| seeking),
+            )
+        };
+        let encode = |item: Option<&K::Model>| {
+            item.map(|item| {
+                let values = keys.iter().map(|(key, _)| key.value(item)).collect();
+                cursor::encode(&secret, &Value::record_from_vec(values))
+            })
+            .transpose()
+        };
+        let (cursor, prev_cursor) = (encode(next)?, encode(prev)?);
+
+        let total = if self.include_total {
+            let count = Query::<List<K::Model>>::all().filter(filter).count();
+            Some(count.exec(&mut state.db).await?)
+        } else {
+            None
+        };
+        Ok(SerializablePage {
+            items,
+            cursor,
+            prev_cursor,
+            total,
+        })
+    }
+}
+
+/// Sorting for listings that return every item at once.
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct SortParams {
+    /// Sort by id, ascending (oldest first) by default.
+    pub order: Option<SortOrder>,
+    /// Comma-separated fields to sort by, each optionally prefixed with `-`
+    /// for descending order, e.g. `-created_at,id`. Supersedes `order`.
+    /// Jokes also accept `top`, short for `-score`.
+    pub sort: Option<String>,
+    // Pagination parameters, kept only to reject them.
+    #[param(ignore)]
+    cursor: Option<String>,
+    #[param(ignore)]
+    before: Option<String>,
+    #[param(ignore)]
+    page_size: Option<String>,
+    #[param(ignore)]
+    include_total: Option<String>,
+}
+
+impl SortParams {
+    /// Every item matching `filter`, in the requested sort order. Fails if
+    /// the request asked for a page, which this listing does not serve.
+    pub async fn fetch_all<K: SortKey>(
+        &self,
+        filter: impl IntoExpr<bool>,
+        state: &mut AppState,
+    ) -> Result<Vec<K::Model>, AppError> {
+        let paging = [
+            ("cursor", &self.cursor),
+            ("before", &self.before),
+            ("page_size", &self.page_size),
+            ("include_total", &self.include_total),
+        ];
+        if let Some((name, _)) = paging.iter().find(|(_, value)| value.is_some()) {
+            return Err(AppError::BadRequest(format!(
+                "`{name}` is not supported by this listing; use its paginated form"
+            )));
+        }
+        let query = sorted(
+            Query::<List<K::Model>>::all().filter(filter.into_expr()),
+            &sort_keys::<K>(self.sort.as_deref(), self.order)?,
+            false,
+        );
+        Ok(query.exec(&mut state.db).await?)
+    }
+}
+
+/// Parse `sort` against the whitelist in `K`, always ending with the id,
+/// which sorts in `order` unless `sort` names it.
+fn sort_keys<K: SortKey>(
+    sort: Option<&str>,
+    order: Option<SortOrder>,
+) -> Result<Vec<(K, SortOrder)>, AppError> {
+    let mut keys: Vec<(K, SortOrder)> = Vec::new();
+    if let Some(sort) = sort {
+        if order.is_some() {
+            return Err(AppError::BadRequest(
+                "`sort` and `order` cannot be combined".to_string(),
+            ));
+        }
+        for field in sort.split(',').map(str::trim) {
+            let field = K::alias(field).unwrap_or(field);
+            let (name, order) = match field.strip_prefix('-') {
+                Some(name) => (name, SortOrder::Desc),
+                None => (field, SortOrder::Asc),
+            };
+            let key = K::parse(name).ok_or_else(|| {
+                AppError::BadRequest(format!(
+                    "Cannot sort by `{name}`; expected one of: {}",
+                    K::NAMES.join(", ")
+                ))
+            })?;
+            if keys.iter().any(|(seen, _)| *seen == key) {
+                return Err(AppError::BadRequest(format!(
+                    "`{name}` appears more than once in `sort`"
+                )));
+            }
+            keys.push((key, order));
+        }
+    }
+    if !keys.iter().any(|(key, _)| *key == K::ID) {
+        keys.push((K::ID, order.unwrap_or_default()));
+    }
+    Ok(keys)
+}
+
+fn sorted<M, K: SortKey>(
+    mut query: Query<List<M>>,
+    keys: &[(K, SortOrder)],
+    reverse: bool,
+) -> Query<List<M>> {
+    for &(key, order) in keys {
+        let order = if reverse { order.reverse() } else { order };
+        query = query.order_by(key.order_by(order));
+    }
+    query
+}
+
+/// Rows sorting strictly after `cursor` (before it, if `backward`):
+/// `k1 > v1 OR (k1 = v1 AND k2 > v2) OR ...`, flipping `>` for descending keys.
+fn keyset<K: SortKey>(
+    keys: &[(K, SortOrder)],
+    cursor: Value,
+    backward: bool,
+) -> Result<Expr<bool>, AppError> {
+    let invalid = || AppError::BadRequest("Invalid pagination cursor".to_string());
+    let Value::Record(record) = cursor else {
+        return Err(invalid());
+    };
+    if record.len() != keys.len() {
+        return Err(invalid());
+    }
+    let mut branches = Vec::with_capacity(keys.len());
+    for (i, &(key, order)) in keys.iter().enumerate() {
+        let mut conditions = Vec::with_capacity(i + 1);
+        for (&(prefix, _), value) in keys.iter().zip(&record.fields).take(i) {
+            conditions.push(
+                prefix
+                    .compare(Comparison::Eq, value.clone())
+                    .ok_or_else(invalid)?,
+            );
+        }
+        let op = match (order, backward) {
+            (SortOrder::Asc, false) | (SortOrder::Desc, true) => Comparison::Gt,
+            (SortOrder::Asc, true) | (SortOrder::Desc, false) => Comparison::Lt,
+        };
+        conditions.push(
+            key.compare(op, record.fields[i].clone())
+                .ok_or_else(invalid)?,
+        );
+        branches.push(Expr::and_all(conditions));
+    }
+    Ok(branches
+        .into_iter()
+        .reduce(Expr::or)
+        .expect("sort keys always include the id"))
+}
+
+/// A page response carrying RFC 8288 `Link` headers to its neighbours.
+pub struct Paginated<T> {
+    page: SerializablePage<T>,
+    uri: Uri,
+}
+
+impl<T> Paginated<T> {
+    /// `uri` is the request URI; its query string is reused for the links
+    /// with only the cursor swapped out.
+    pub fn new(page: SerializablePage<T>, uri: Uri) -> Self {
+        Self { page, uri }
+    }
+
+    fn link(&self, param: &str, cursor: &str, rel: &str) -> String {
+        let query = self
+            .uri
+            .query()
+            .unwrap_or_default()
+            .split('&')
+            .filter(|pair| {
+                let name = pair.split('=').next().unwrap_or_default();
+                !pair.is_empty() && name != "cursor" && name != "before"
+            })
+            .chain([format!("{param}={cursor}").as_str()])
+            .collect::<Vec<_>>()
+            .join("&");
+        format!("<{}?{query}>; rel=\"{rel}\"", self.uri.path())
+    }
+}
+
+impl<T: Serialize> IntoResponse for Paginated<T> {
+    fn into_response(self) -> Response {
+        let links = [
+            self.page
+                .cursor
+                .as_deref()
+                .map(|cursor| self.link("cursor", cursor, "next")),
+            self.page
+                .prev_cursor
+                .as_deref()
+                .map(|cursor| self.link("before", cursor, "prev")),
+        ]
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>()
+        .join(", ");
+        let mut response = Json(self.page).into_response();
+        if let Ok(value) = HeaderValue::from_str(&links)
+            && !links.is_empty()
+        {
+            response.headers_mut().insert(header::LINK, value);
+        }
+        response
+    }
+}
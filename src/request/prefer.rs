@@ -0,0 +1,65 @@
+use std::convert::Infallible;
+
+use axum::{
+    Json,
+    extract::FromRequestParts,
+    http::{StatusCode, header::HeaderName, request::Parts},
+    response::{IntoResponse, Response},
+};
+use serde::Serialize;
+
+static PREFER: HeaderName = HeaderName::from_static("prefer");
+static PREFERENCE_APPLIED: HeaderName = HeaderName::from_static("preference-applied");
+
+/// The `return` preference of an RFC 7240 `Prefer` request header.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ReturnPreference {
+    #[default]
+    Representation,
+    Minimal,
+}
+
+impl ReturnPreference {
+    /// Respond with `body`, or with `204 No Content` if the client asked for
+    /// `return=minimal`.
+    pub fn respond<T: Serialize>(self, body: T) -> Response {
+        match self {
+            Self::Representation => Json(body).into_response(),
+            Self::Minimal => (
+                StatusCode::NO_CONTENT,
+                [(PREFERENCE_APPLIED.clone(), "return=minimal")],
+            )
+                .into_response(),
+        }
+    }
+}
+
+impl<S> FromRequestParts<S> for ReturnPreference
+where
+    S: Send + Sync,
+{
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let minimal = parts
+            .headers
+            .get_all(&PREFER)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .filter_map(|preference| preference.split(';').next())
+            .filter_map(|preference| preference.split_once('='))
+            .any(|(name, value)| {
+                name.trim().eq_ignore_ascii_case("return")
+                    && value
+                        .trim()
+                        .trim_matches('"')
+                        .eq_ignore_ascii_case("minimal")
+            });
+        Ok(if minimal {
+            Self::Minimal
+        } else {
+            Self::Representation
+        })
+    }
+}
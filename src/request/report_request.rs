@@ -0,0 +1,104 @@
+use serde::{Deserialize, Serialize};
+use toasty::stmt::{Expr, OrderBy, Value};
+use utoipa::{IntoParams, ToSchema};
+use validator::{Validate, ValidationError};
+
+use crate::{
+    request::pagination::{Comparison, SortKey, SortOrder},
+    schemas::report::{Report, ReportReason, ReportStatus},
+};
+
+#[derive(Debug, Serialize, Deserialize, Validate, ToSchema)]
+pub struct ReportRequest {
+    pub reason: ReportReason,
+    /// Anything the moderators should know.
+    #[validate(length(max = 1000, message = "Report note must be at most 1000 characters"))]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate, ToSchema)]
+pub struct ResolveReportRequest {
+    /// `resolved` or `dismissed`.
+    #[validate(custom(function = "validate_closed"))]
+    pub status: ReportStatus,
+    /// Also hide the reported joke, closing its other open reports.
+    #[serde(default)]
+    pub hide_joke: bool,
+}
+
+fn validate_closed(status: &ReportStatus) -> Result<(), ValidationError> {
+    if *status == ReportStatus::Open {
+        Err(ValidationError::new("status")
+            .with_message("A report can only be resolved or dismissed".into()))
+    } else {
+        Ok(())
+    }
+}
+
+/// Filters for the moderation queue.
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ReportFilter {
+    /// Only reports in this state; defaults to `open`.
+    pub status: Option<ReportStatus>,
+    /// Only reports about this joke.
+    pub joke_id: Option<i64>,
+}
+
+impl ReportFilter {
+    pub fn to_expr(&self) -> Expr<bool> {
+        let status = self.status.unwrap_or(ReportStatus::Open);
+        let mut conditions = vec![Report::fields().status().eq(status)];
+        if let Some(joke_id) = self.joke_id {
+            conditions.push(Report::fields().joke_id().eq(joke_id));
+        }
+        Expr::and_all(conditions)
+    }
+}
+
+/// Fields report listings can be sorted by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReportSort {
+    Id,
+    CreatedAt,
+}
+
+impl SortKey for ReportSort {
+    type Model = Report;
+
+    const ID: Self = Self::Id;
+    const NAMES: &'static [&'static str] = &["id", "created_at"];
+
+    fn parse(name: &str) -> Option<Self> {
+        match name {
+            "id" => Some(Self::Id),
+            "created_at" => Some(Self::CreatedAt),
+            _ => None,
+        }
+    }
+
+    fn order_by(self, order: SortOrder) -> OrderBy {
+        match self {
+            Self::Id => order.by(Report::fields().id()),
+            Self::CreatedAt => order.by(Report::fields().created_at()),
+        }
+    }
+
+    fn value(self, report: &Report) -> Value {
+        match self {
+            Self::Id => Value::I64(report.id),
+            Self::CreatedAt => Value::Timestamp(report.created_at),
+        }
+    }
+
+    fn compare(self, op: Comparison, value: Value) -> Option<Expr<bool>> {
+        match (self, value) {
+            (Self::Id, Value::I64(id)) => Some(op.apply(Report::fields().id(), id)),
+            (Self::CreatedAt, Value::Timestamp(at)) => {
+                Some(op.apply(Report::fields().created_at(), at))
+            }
+            _ => None,
+        }
+    }
+}
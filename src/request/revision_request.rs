@@ -0,0 +1,52 @@
+use toasty::stmt::{Expr, OrderBy, Value};
+
+use crate::{
+    request::pagination::{Comparison, SortKey, SortOrder},
+    schemas::joke_revision::JokeRevision,
+};
+
+/// Fields revision listings can be sorted by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RevisionSort {
+    Id,
+    WrittenAt,
+}
+
+impl SortKey for RevisionSort {
+    type Model = JokeRevision;
+
+    const ID: Self = Self::Id;
+    const NAMES: &'static [&'static str] = &["id", "written_at"];
+
+    fn parse(name: &str) -> Option<Self> {
+        match name {
+            "id" => Some(Self::Id),
+            "written_at" => Some(Self::WrittenAt),
+            _ => None,
+        }
+    }
+
+    fn order_by(self, order: SortOrder) -> OrderBy {
+        match self {
+            Self::Id => order.by(JokeRevision::fields().id()),
+            Self::WrittenAt => order.by(JokeRevision::fields().written_at()),
+        }
+    }
+
+    fn value(self, revision: &JokeRevision) -> Value {
+        match self {
+            Self::Id => Value::I64(revision.id),
+            Self::WrittenAt => Value::Timestamp(revision.written_at),
+        }
+    }
+
+    fn compare(self, op: Comparison, value: Value) -> Option<Expr<bool>> {
+        match (self, value) {
+            (Self::Id, Value::I64(id)) => Some(op.apply(JokeRevision::fields().id(), id)),
+            (Self::WrittenAt, Value::Timestamp(at)) => {
+                Some(op.apply(JokeRevision::fields().written_at(), at))
+            }
+            _ => None,
+        }
+    }
+}
@@ -1,7 +1,13 @@
 use serde::{Deserialize, Serialize};
+use toasty::stmt::{Expr, OrderBy, Value};
 use utoipa::ToSchema;
 use validator::Validate;
 
+use crate::{
+    request::pagination::{Comparison, SortKey, SortOrder},
+    schemas::user::{Role, User},
+};
+
 #[derive(Debug, Serialize, Deserialize, Validate, ToSchema)]
 pub struct UserRequest {
     #[validate(length(
@@ -13,3 +19,74 @@ pub struct UserRequest {
     #[validate(email(message = "User email must be a valid email address"))]
     pub email: String,
 }
+
+/// Partial update of a user; omitted (or `null`) fields are left unchanged.
+#[derive(Debug, Default, Serialize, Deserialize, Validate, ToSchema)]
+pub struct UserPatchRequest {
+    #[validate(length(
+        min = 1,
+        max = 255,
+        message = "User name must be between 1 and 255 characters"
+    ))]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[validate(email(message = "User email must be a valid email address"))]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate, ToSchema)]
+pub struct RoleRequest {
+    pub role: Role,
+}
+
+/// Fields user listings can be sorted by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UserSort {
+    Id,
+    Name,
+    CreatedAt,
+}
+
+impl SortKey for UserSort {
+    type Model = User;
+
+    const ID: Self = Self::Id;
+    const NAMES: &'static [&'static str] = &["id", "name", "created_at"];
+
+    fn parse(name: &str) -> Option<Self> {
+        match name {
+            "id" => Some(Self::Id),
+            "name" => Some(Self::Name),
+            "created_at" => Some(Self::CreatedAt),
+            _ => None,
+        }
+    }
+
+    fn order_by(self, order: SortOrder) -> OrderBy {
+        match self {
+            Self::Id => order.by(User::fields().id()),
+            Self::Name => order.by(User::fields().name()),
+            Self::CreatedAt => order.by(User::fields().created_at()),
+        }
+    }
+
+    fn value(self, user: &User) -> Value {
+        match self {
+            Self::Id => Value::I64(user.id),
+            Self::Name => Value::String(user.name.clone()),
+            Self::CreatedAt => Value::Timestamp(user.created_at),
+        }
+    }
+
+    fn compare(self, op: Comparison, value: Value) -> Option<Expr<bool>> {
+        match (self, value) {
+            (Self::Id, Value::I64(id)) => Some(op.apply(User::fields().id(), id)),
+            (Self::Name, Value::String(name)) => Some(op.apply(User::fields().name(), name)),
+            (Self::CreatedAt, Value::Timestamp(at)) => {
+                Some(op.apply(User::fields().created_at(), at))
+            }
+            _ => None,
+        }
+    }
+}
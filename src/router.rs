@@ -1,19 +1,18 @@
-use axum::Router;
+use axum::{Router, middleware};
 use tower_http::{cors::CorsLayer, trace::TraceLayer};
 use utoipa::OpenApi;
 use utoipa_axum::router::OpenApiRouter;
 use utoipa_swagger_ui::SwaggerUi;
 
+use crate::auth;
 use crate::handlers;
-use crate::openapi::ApiDoc;
+use crate::openapi::{self, ApiDoc};
 use crate::state::AppState;
 
 /// Create the Axum router with all routes.
 /// Public for integration testing.
 pub fn create_app(state: AppState) -> Router {
-    let (router, api) = OpenApiRouter::with_openapi(ApiDoc::openapi())
-        .routes(utoipa_axum::routes!(handlers::health::index))
-        .routes(utoipa_axum::routes!(handlers::health::health))
+    let (router, mut api) = OpenApiRouter::with_openapi(ApiDoc::openapi())
         .routes(utoipa_axum::routes!(
             handlers::users::get_all_users,
             handlers::users::add_user,
@@ -22,8 +21,11 @@ pub fn create_app(state: AppState) -> Router {
         .routes(utoipa_axum::routes!(
             handlers::users::get_user,
             handlers::users::update_user,
+            handlers::users::patch_user,
             handlers::users::delete_user,
         ))
+        .routes(utoipa_axum::routes!(handlers::users::update_user_role))
+        .routes(utoipa_axum::routes!(handlers::users::restore_user))
         .routes(utoipa_axum::routes!(
             handlers::jokes::get_user_jokes,
             handlers::jokes::add_joke,
@@ -33,15 +35,66 @@ pub fn create_app(state: AppState) -> Router {
             handlers::jokes::delete_all_jokes,
         ))
         .routes(utoipa_axum::routes!(handlers::jokes::paginate_jokes))
+        .routes(utoipa_axum::routes!(handlers::jokes::search_jokes))
+        .routes(utoipa_axum::routes!(handlers::jokes::random_joke))
+        .routes(utoipa_axum::routes!(handlers::jokes::daily_joke))
         .routes(utoipa_axum::routes!(
             handlers::jokes::get_joke,
             handlers::jokes::update_joke,
+            handlers::jokes::patch_joke,
             handlers::jokes::delete_joke,
         ))
+        .routes(utoipa_axum::routes!(handlers::jokes::restore_joke))
+        .routes(utoipa_axum::routes!(handlers::revisions::get_revisions))
+        .routes(utoipa_axum::routes!(handlers::revisions::revert_revision))
+        .routes(utoipa_axum::routes!(
+            handlers::comments::get_comments,
+            handlers::comments::add_comment,
+        ))
+        .routes(utoipa_axum::routes!(
+            handlers::comments::get_comment,
+            handlers::comments::update_comment,
+            handlers::comments::delete_comment,
+        ))
+        .routes(utoipa_axum::routes!(
+            handlers::favorites::put_favorite,
+            handlers::favorites::delete_favorite,
+        ))
+        .routes(utoipa_axum::routes!(
+            handlers::favorites::get_user_favorites
+        ))
+        .routes(utoipa_axum::routes!(
+            handlers::reactions::put_reaction,
+            handlers::reactions::delete_reaction,
+        ))
+        .routes(utoipa_axum::routes!(handlers::reports::report_joke))
+        .routes(utoipa_axum::routes!(
+            handlers::reports::hide_joke,
+            handlers::reports::unhide_joke,
+        ))
+        .routes(utoipa_axum::routes!(handlers::reports::get_reports))
+        .routes(utoipa_axum::routes!(handlers::reports::resolve_report))
+        .routes(utoipa_axum::routes!(handlers::tags::get_all_tags))
+        .routes(utoipa_axum::routes!(handlers::tags::get_tag_jokes))
+        .routes(utoipa_axum::routes!(
+            handlers::votes::put_vote,
+            handlers::votes::delete_vote,
+        ))
+        .route_layer(middleware::from_fn_with_state(
+            state.clone(),
+            auth::authenticate,
+        ))
+        // Routes below this point are never behind authentication.
+        .routes(utoipa_axum::routes!(handlers::health::index))
+        .routes(utoipa_axum::routes!(handlers::health::health))
+        .routes(utoipa_axum::routes!(handlers::auth::register))
+        .routes(utoipa_axum::routes!(handlers::auth::login))
+        .routes(utoipa_axum::routes!(handlers::auth::refresh))
         .layer(CorsLayer::very_permissive())
         .layer(TraceLayer::new_for_http())
         .with_state(state)
         .split_for_parts();
+    openapi::document_problem_responses(&mut api);
 
     router.merge(SwaggerUi::new("/api").url("/api-docs/openapi.json", api))
 }
@@ -0,0 +1,20 @@
+use toasty::Model;
+
+use crate::schemas::user::User;
+
+/// A hashed API key. Keys without a user are operator keys issued from the CLI.
+#[derive(Debug, Clone, Model)]
+pub struct ApiKey {
+    #[key]
+    #[auto]
+    pub id: i64,
+    pub name: String,
+    #[unique]
+    pub key_hash: String,
+    #[index]
+    pub user_id: Option<i64>,
+    #[belongs_to]
+    pub user: toasty::Deferred<Option<User>>,
+    #[auto]
+    pub created_at: jiff::Timestamp,
+}
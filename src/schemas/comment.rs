@@ -0,0 +1,46 @@
+use serde::{Deserialize, Serialize};
+use toasty::Model;
+use utoipa::ToSchema;
+
+use crate::schemas::{joke::Joke, user::User};
+
+/// A comment on a joke, or a reply to another comment on the same joke.
+///
+/// Deleting a comment deletes its replies, and deleting a joke or user deletes
+/// their comments.
+#[derive(Debug, Clone, Serialize, Deserialize, Model, ToSchema)]
+pub struct Comment {
+    #[key]
+    #[auto]
+    pub id: i64,
+    pub content: String,
+    #[index]
+    pub joke_id: i64,
+    #[belongs_to]
+    #[serde(skip_serializing_if = "toasty::Deferred::is_unloaded", default)]
+    #[schema(ignore)]
+    pub joke: toasty::Deferred<Joke>,
+    #[index]
+    pub user_id: i64,
+    #[belongs_to]
+    #[serde(skip_serializing_if = "toasty::Deferred::is_unloaded", default)]
+    #[schema(ignore)]
+    pub user: toasty::Deferred<User>,
+    /// The comment this replies to; `None` for top-level comments.
+    #[index]
+    pub parent_id: Option<i64>,
+    #[belongs_to(key = parent_id, references = id)]
+    #[serde(skip)]
+    #[schema(ignore)]
+    pub parent: toasty::Deferred<Option<Comment>>,
+    #[has_many(pair = parent)]
+    #[serde(skip)]
+    #[schema(ignore)]
+    pub replies: toasty::Deferred<Vec<Comment>>,
+    #[auto]
+    #[schema(value_type = String, format = "date-time")]
+    pub created_at: jiff::Timestamp,
+    #[auto]
+    #[schema(value_type = String, format = "date-time")]
+    pub updated_at: jiff::Timestamp,
+}
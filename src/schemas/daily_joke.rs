@@ -0,0 +1,15 @@
+use toasty::Model;
+
+/// The joke picked for one calendar day, kept so the pick is stable for the
+/// whole day and is not repeated too soon.
+#[derive(Debug, Clone, Model)]
+pub struct DailyJoke {
+    #[key]
+    #[auto]
+    pub id: i64,
+    #[unique]
+    pub day: jiff::civil::Date,
+    /// Not a relation: the joke may since have been deleted.
+    #[index]
+    pub joke_id: i64,
+}
@@ -0,0 +1,25 @@
+use serde::{Deserialize, Serialize};
+use toasty::Model;
+use utoipa::ToSchema;
+
+use crate::schemas::{joke::Joke, user::User};
+
+/// A joke a user has saved.
+#[derive(Debug, Clone, Serialize, Deserialize, Model, ToSchema)]
+#[key(user_id, joke_id)]
+pub struct Favorite {
+    pub user_id: i64,
+    #[belongs_to]
+    #[serde(skip_serializing_if = "toasty::Deferred::is_unloaded", default)]
+    #[schema(ignore)]
+    pub user: toasty::Deferred<User>,
+    #[index]
+    pub joke_id: i64,
+    #[belongs_to]
+    #[serde(skip_serializing_if = "toasty::Deferred::is_unloaded", default)]
+    #[schema(ignore)]
+    pub joke: toasty::Deferred<Joke>,
+    #[auto]
+    #[schema(value_type = String, format = "date-time")]
+    pub created_at: jiff::Timestamp,
+}
@@ -2,7 +2,15 @@ use serde::{Deserialize, Serialize};
 use toasty::Model;
 use utoipa::ToSchema;
 
-use crate::schemas::user::User;
+use std::collections::BTreeMap;
+
+use crate::schemas::{
+    comment::Comment, favorite::Favorite, joke_revision::JokeRevision, joke_tag::JokeTag,
+    reaction::Reaction, report::Report, tag::Tag, user::User, vote::Vote,
+};
+
+/// Reaction counts by kind, as stored in [`Joke::reaction_counts`].
+pub type ReactionCounts = BTreeMap<String, i64>;
 
 #[derive(Debug, Clone, Serialize, Deserialize, Model, ToSchema)]
 pub struct Joke {
@@ -16,6 +24,60 @@ pub struct Joke {
     #[serde(skip_serializing_if = "toasty::Deferred::is_unloaded", default)]
     #[schema(ignore)]
     pub user: toasty::Deferred<User>,
+    /// Sum of the joke's votes.
+    #[index]
+    #[default(0)]
+    pub score: i64,
+    /// Number of users who saved the joke.
+    #[default(0)]
+    pub favorite_count: i64,
+    /// Number of reactions of each kind; kinds nobody used are left out.
+    #[default(toasty::Json(ReactionCounts::new()))]
+    #[serde(rename = "reactions", with = "json")]
+    #[schema(value_type = BTreeMap<String, i64>)]
+    pub reaction_counts: toasty::Json<ReactionCounts>,
+    /// Hidden by a moderator; left out of joke listings.
+    #[index]
+    #[default(false)]
+    pub hidden: bool,
+    /// When the joke was deleted; it can be restored until it is purged.
+    #[index]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<String>, format = "date-time")]
+    pub deleted_at: Option<jiff::Timestamp>,
+    #[has_many]
+    #[serde(skip_serializing_if = "toasty::Deferred::is_unloaded", default)]
+    #[schema(ignore)]
+    pub votes: toasty::Deferred<Vec<Vote>>,
+    #[has_many]
+    #[serde(skip_serializing_if = "toasty::Deferred::is_unloaded", default)]
+    #[schema(ignore)]
+    pub reports: toasty::Deferred<Vec<Report>>,
+    #[has_many]
+    #[serde(skip)]
+    #[schema(ignore)]
+    pub reactions: toasty::Deferred<Vec<Reaction>>,
+    #[has_many]
+    #[serde(skip_serializing_if = "toasty::Deferred::is_unloaded", default)]
+    #[schema(ignore)]
+    pub favorites: toasty::Deferred<Vec<Favorite>>,
+    #[has_many]
+    #[serde(skip_serializing_if = "toasty::Deferred::is_unloaded", default)]
+    #[schema(ignore)]
+    pub comments: toasty::Deferred<Vec<Comment>>,
+    #[has_many]
+    #[serde(skip)]
+    #[schema(ignore)]
+    pub revisions: toasty::Deferred<Vec<JokeRevision>>,
+    #[has_many]
+    #[serde(skip)]
+    #[schema(ignore)]
+    pub joke_tags: toasty::Deferred<Vec<JokeTag>>,
+    /// Only included when a single joke is returned.
+    #[has_many(via = joke_tags.tag)]
+    #[serde(skip_serializing_if = "toasty::Deferred::is_unloaded", default)]
+    #[schema(value_type = Option<Vec<Tag>>)]
+    pub tags: toasty::Deferred<Vec<Tag>>,
     #[auto]
     #[schema(value_type = String, format = "date-time")]
     pub created_at: jiff::Timestamp,
@@ -23,3 +85,21 @@ pub struct Joke {
     #[schema(value_type = String, format = "date-time")]
     pub updated_at: jiff::Timestamp,
 }
+
+/// Serde for [`toasty::Json`] columns, which toasty leaves to the model.
+mod json {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<T: Serialize, S: Serializer>(
+        value: &toasty::Json<T>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        value.0.serialize(serializer)
+    }
+
+    pub fn deserialize<'de, T: Deserialize<'de>, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<toasty::Json<T>, D::Error> {
+        T::deserialize(deserializer).map(toasty::Json)
+    }
+}
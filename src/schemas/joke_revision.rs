@@ -0,0 +1,29 @@
+use serde::{Deserialize, Serialize};
+use toasty::Model;
+use utoipa::ToSchema;
+
+use crate::schemas::joke::Joke;
+
+/// One version of a joke's content. The newest revision of a joke matches
+/// its current content.
+///
+/// Jokes written before revisions were recorded get their original version
+/// backfilled on their first edit.
+#[derive(Debug, Clone, Serialize, Deserialize, Model, ToSchema)]
+pub struct JokeRevision {
+    #[key]
+    #[auto]
+    pub id: i64,
+    #[index]
+    pub joke_id: i64,
+    #[belongs_to]
+    #[serde(skip_serializing_if = "toasty::Deferred::is_unloaded", default)]
+    #[schema(ignore)]
+    pub joke: toasty::Deferred<Joke>,
+    pub content: String,
+    /// Who wrote this version; `None` if it was written with an operator key.
+    pub author_id: Option<i64>,
+    /// When this version was written.
+    #[schema(value_type = String, format = "date-time")]
+    pub written_at: jiff::Timestamp,
+}
@@ -0,0 +1,16 @@
+use toasty::Model;
+
+use crate::schemas::{joke::Joke, tag::Tag};
+
+/// Attaches a [`Tag`] to a [`Joke`].
+#[derive(Debug, Clone, Model)]
+#[key(joke_id, tag_id)]
+pub struct JokeTag {
+    pub joke_id: i64,
+    #[belongs_to]
+    pub joke: toasty::Deferred<Joke>,
+    #[index]
+    pub tag_id: i64,
+    #[belongs_to]
+    pub tag: toasty::Deferred<Tag>,
+}
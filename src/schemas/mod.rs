@@ -1,2 +1,13 @@
+pub mod api_key;
+pub mod comment;
+pub mod daily_joke;
+pub mod favorite;
 pub mod joke;
+pub mod joke_revision;
+pub mod joke_tag;
+pub mod reaction;
+pub mod refresh_token;
+pub mod report;
+pub mod tag;
 pub mod user;
+pub mod vote;
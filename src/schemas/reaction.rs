@@ -0,0 +1,55 @@
+use serde::{Deserialize, Serialize};
+use toasty::Model;
+use utoipa::ToSchema;
+
+use crate::schemas::{joke::Joke, user::User};
+
+/// The reactions users can leave on jokes, named by their emoji shortcodes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ReactionKind {
+    /// 😂
+    Joy,
+    /// 🙄
+    RollEyes,
+    /// 🤔
+    Thinking,
+}
+
+impl ReactionKind {
+    pub const ALL: [Self; 3] = [Self::Joy, Self::RollEyes, Self::Thinking];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Joy => "joy",
+            Self::RollEyes => "roll_eyes",
+            Self::Thinking => "thinking",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|kind| kind.as_str() == name)
+    }
+}
+
+/// A user's reaction of one kind to a joke; a user may leave several kinds.
+#[derive(Debug, Clone, Serialize, Deserialize, Model, ToSchema)]
+#[key(user_id, joke_id, kind)]
+pub struct Reaction {
+    pub user_id: i64,
+    #[belongs_to]
+    #[serde(skip_serializing_if = "toasty::Deferred::is_unloaded", default)]
+    #[schema(ignore)]
+    pub user: toasty::Deferred<User>,
+    #[index]
+    pub joke_id: i64,
+    #[belongs_to]
+    #[serde(skip_serializing_if = "toasty::Deferred::is_unloaded", default)]
+    #[schema(ignore)]
+    pub joke: toasty::Deferred<Joke>,
+    /// One of the [`ReactionKind`] names.
+    pub kind: String,
+    #[auto]
+    #[schema(value_type = String, format = "date-time")]
+    pub created_at: jiff::Timestamp,
+}
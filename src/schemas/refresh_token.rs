@@ -0,0 +1,21 @@
+use toasty::Model;
+
+use crate::schemas::user::User;
+
+/// An issued refresh token, tracked so it can be rotated and revoked.
+#[derive(Debug, Clone, Model)]
+pub struct RefreshToken {
+    #[key]
+    #[auto]
+    pub id: i64,
+    #[unique]
+    pub jti: String,
+    #[index]
+    pub user_id: i64,
+    #[belongs_to]
+    pub user: toasty::Deferred<User>,
+    pub expires_at: jiff::Timestamp,
+    pub revoked_at: Option<jiff::Timestamp>,
+    #[auto]
+    pub created_at: jiff::Timestamp,
+}
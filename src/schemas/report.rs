@@ -0,0 +1,70 @@
+use serde::{Deserialize, Serialize};
+use toasty::{Embed, Model};
+use utoipa::ToSchema;
+
+use crate::schemas::{joke::Joke, user::User};
+
+/// Why a joke was reported.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Embed, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ReportReason {
+    #[column(variant = 1)]
+    Offensive,
+    #[column(variant = 2)]
+    Harassment,
+    #[column(variant = 3)]
+    Spam,
+    #[column(variant = 4)]
+    Other,
+}
+
+/// Where a report is in the moderation queue.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Embed, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ReportStatus {
+    /// Waiting for a moderator.
+    #[column(variant = 1)]
+    Open,
+    /// A moderator acted on it.
+    #[column(variant = 2)]
+    Resolved,
+    /// A moderator found nothing wrong.
+    #[column(variant = 3)]
+    Dismissed,
+}
+
+/// A user's complaint about a joke, queued for moderators.
+#[derive(Debug, Clone, Serialize, Deserialize, Model, ToSchema)]
+pub struct Report {
+    #[key]
+    #[auto]
+    pub id: i64,
+    #[index]
+    pub reporter_id: i64,
+    #[belongs_to(key = reporter_id, references = id)]
+    #[serde(skip_serializing_if = "toasty::Deferred::is_unloaded", default)]
+    #[schema(ignore)]
+    pub reporter: toasty::Deferred<User>,
+    #[index]
+    pub joke_id: i64,
+    #[belongs_to]
+    #[serde(skip_serializing_if = "toasty::Deferred::is_unloaded", default)]
+    #[schema(ignore)]
+    pub joke: toasty::Deferred<Joke>,
+    pub reason: ReportReason,
+    pub note: Option<String>,
+    #[index]
+    #[default(ReportStatus::Open)]
+    pub status: ReportStatus,
+    /// The user id of the moderator who closed the report; `None` while open
+    /// or if it was closed with an operator key.
+    pub resolved_by: Option<i64>,
+    #[schema(value_type = Option<String>, format = "date-time")]
+    pub resolved_at: Option<jiff::Timestamp>,
+    #[auto]
+    #[schema(value_type = String, format = "date-time")]
+    pub created_at: jiff::Timestamp,
+    #[auto]
+    #[schema(value_type = String, format = "date-time")]
+    pub updated_at: jiff::Timestamp,
+}
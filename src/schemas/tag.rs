@@ -0,0 +1,30 @@
+use serde::{Deserialize, Serialize};
+use toasty::Model;
+use utoipa::ToSchema;
+
+use crate::schemas::{joke::Joke, joke_tag::JokeTag};
+
+/// A label classifying jokes, attached to them through [`JokeTag`].
+#[derive(Debug, Clone, Serialize, Deserialize, Model, ToSchema)]
+pub struct Tag {
+    #[key]
+    #[auto]
+    pub id: i64,
+    #[unique]
+    pub name: String,
+    #[has_many]
+    #[serde(skip)]
+    #[schema(ignore)]
+    pub joke_tags: toasty::Deferred<Vec<JokeTag>>,
+    #[has_many(via = joke_tags.joke)]
+    #[serde(skip_serializing_if = "toasty::Deferred::is_unloaded", default)]
+    #[schema(ignore)]
+    pub jokes: toasty::Deferred<Vec<Joke>>,
+}
+
+/// A tag and how many jokes carry it.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct TagUsage {
+    pub name: String,
+    pub jokes: u64,
+}
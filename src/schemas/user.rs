@@ -1,8 +1,25 @@
 use serde::{Deserialize, Serialize};
-use toasty::Model;
+use toasty::{Embed, Model};
 use utoipa::ToSchema;
 
-use crate::schemas::joke::Joke;
+use crate::schemas::{
+    comment::Comment, favorite::Favorite, joke::Joke, reaction::Reaction, report::Report,
+    vote::Vote,
+};
+
+/// Access level of a user, ordered from least to most privileged.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, Embed, ToSchema,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    #[column(variant = 1)]
+    User,
+    #[column(variant = 2)]
+    Moderator,
+    #[column(variant = 3)]
+    Admin,
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize, Model, ToSchema)]
 pub struct User {
@@ -12,10 +29,40 @@ pub struct User {
     pub name: String,
     #[unique]
     pub email: String,
+    #[default(Role::User)]
+    pub role: Role,
+    /// Argon2 PHC string; `None` for users that cannot log in with a password.
+    #[serde(skip)]
+    pub password_hash: Option<String>,
+    /// When the user was deleted; they can be restored until they are purged.
+    #[index]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<String>, format = "date-time")]
+    pub deleted_at: Option<jiff::Timestamp>,
     #[has_many]
     #[serde(skip_serializing_if = "toasty::Deferred::is_unloaded", default)]
     #[schema(ignore)]
     pub jokes: toasty::Deferred<Vec<Joke>>,
+    #[has_many]
+    #[serde(skip_serializing_if = "toasty::Deferred::is_unloaded", default)]
+    #[schema(ignore)]
+    pub votes: toasty::Deferred<Vec<Vote>>,
+    #[has_many(pair = reporter)]
+    #[serde(skip_serializing_if = "toasty::Deferred::is_unloaded", default)]
+    #[schema(ignore)]
+    pub reports: toasty::Deferred<Vec<Report>>,
+    #[has_many]
+    #[serde(skip_serializing_if = "toasty::Deferred::is_unloaded", default)]
+    #[schema(ignore)]
+    pub reactions: toasty::Deferred<Vec<Reaction>>,
+    #[has_many]
+    #[serde(skip_serializing_if = "toasty::Deferred::is_unloaded", default)]
+    #[schema(ignore)]
+    pub favorites: toasty::Deferred<Vec<Favorite>>,
+    #[has_many]
+    #[serde(skip_serializing_if = "toasty::Deferred::is_unloaded", default)]
+    #[schema(ignore)]
+    pub comments: toasty::Deferred<Vec<Comment>>,
     #[auto]
     #[schema(value_type = String, format = "date-time")]
     pub created_at: jiff::Timestamp,
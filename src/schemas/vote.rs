@@ -0,0 +1,30 @@
+use serde::{Deserialize, Serialize};
+use toasty::Model;
+use utoipa::ToSchema;
+
+use crate::schemas::{joke::Joke, user::User};
+
+/// A user's up- or down-vote on a joke; each user has at most one per joke.
+#[derive(Debug, Clone, Serialize, Deserialize, Model, ToSchema)]
+#[key(user_id, joke_id)]
+pub struct Vote {
+    pub user_id: i64,
+    #[belongs_to]
+    #[serde(skip_serializing_if = "toasty::Deferred::is_unloaded", default)]
+    #[schema(ignore)]
+    pub user: toasty::Deferred<User>,
+    #[index]
+    pub joke_id: i64,
+    #[belongs_to]
+    #[serde(skip_serializing_if = "toasty::Deferred::is_unloaded", default)]
+    #[schema(ignore)]
+    pub joke: toasty::Deferred<Joke>,
+    /// `1` for an up-vote, `-1` for a down-vote.
+    pub value: i64,
+    #[auto]
+    #[schema(value_type = String, format = "date-time")]
+    pub created_at: jiff::Timestamp,
+    #[auto]
+    #[schema(value_type = String, format = "date-time")]
+    pub updated_at: jiff::Timestamp,
+}
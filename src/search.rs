@@ -0,0 +1,235 @@
+//! Full-text search over joke content, backed by an SQLite FTS5 index.
+//!
+//! `jokes_fts` is an external-content table over `jokes`: triggers keep it in
+//! sync on every insert, update and delete, so the toasty models need not know
+//! it exists.
+
+use serde::{Deserialize, Serialize};
+use toasty::stmt::{Type, Value};
+use utoipa::ToSchema;
+
+use crate::{
+    SerializablePage,
+    error::AppError,
+    request::{cursor, pagination::PaginationParams},
+    schemas::joke::Joke,
+    state::AppState,
+};
+
+/// Private-use characters SQLite wraps matches in, swapped for `<mark>` once
+/// the snippet has been HTML-escaped.
+const MATCH_START: char = '\u{E000}';
+const MATCH_END: char = '\u{E001}';
+
+const SCHEMA: &[&str] = &[
+    "CREATE VIRTUAL TABLE IF NOT EXISTS jokes_fts \
+     USING fts5(content, content='jokes', content_rowid='id')",
+    "CREATE TRIGGER IF NOT EXISTS jokes_fts_insert AFTER INSERT ON jokes BEGIN \
+     INSERT INTO jokes_fts (rowid, content) VALUES (new.id, new.content); \
+     END",
+    "CREATE TRIGGER IF NOT EXISTS jokes_fts_delete AFTER DELETE ON jokes BEGIN \
+     INSERT INTO jokes_fts (jokes_fts, rowid, content) VALUES ('delete', old.id, old.content); \
+     END",
+    "CREATE TRIGGER IF NOT EXISTS jokes_fts_update AFTER UPDATE OF content ON jokes BEGIN \
+     INSERT INTO jokes_fts (jokes_fts, rowid, content) VALUES ('delete', old.id, old.content); \
+     INSERT INTO jokes_fts (rowid, content) VALUES (new.id, new.content); \
+     END",
+];
+
+/// Create the search index and its triggers. Idempotent; run after
+/// `push_schema` on every startup.
+///
+/// Jokes written before the index existed are indexed when it is created;
+/// after that the triggers keep it current, so later startups skip the
+/// rebuild, which reads every joke.
+pub async fn install(db: &mut toasty::Db) -> Result<(), toasty::Error> {
+    let existing = toasty::sql::query(
+        "SELECT name FROM sqlite_master WHERE type = 'table' AND name = 'jokes_fts'",
+    )
+    .column_types([Type::String])
+    .exec(db)
+    .await?;
+    for sql in SCHEMA {
+        toasty::sql::statement(*sql).exec(db).await?;
+    }
+    if existing.is_empty() {
+        toasty::sql::statement("INSERT INTO jokes_fts (jokes_fts) VALUES ('rebuild')")
+            .exec(db)
+            .await?;
+    }
+    Ok(())
+}
+
+/// A joke matching a search, with the matched terms highlighted.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct SearchHit {
+    pub joke: Joke,
+    /// HTML-escaped excerpt of the content with matches wrapped in `<mark>`.
+    pub snippet: String,
+}
+
+/// Run `terms` against the index and return the requested page of hits,
+/// best first.
+///
+/// Relevance ranks are not stable keys, so search cursors carry an offset
+/// into the ranked results instead of a keyset.
+pub async fn search(
+    terms: &str,
+    params: &PaginationParams,
+    state: &mut AppState,
+) -> Result<SerializablePage<SearchHit>, AppError> {
+    if params.sort.is_some() || params.order.is_some() {
+        return Err(AppError::BadRequest(
+            "Search results are ordered by relevance; `sort` and `order` do not apply".to_string(),
+        ));
+    }
+    let expression = match_expression(terms)
+        .ok_or_else(|| AppError::BadRequest("Search query is empty".to_string()))?;
+    let secret = state.config.jwt_secret.clone();
+    let page_size = params.page_size();
+    let (start, limit, backward) = match (&params.cursor, &params.before) {
+        (Some(_), Some(_)) => {
+            return Err(AppError::BadRequest(
+                "`cursor` and `before` cannot be combined".to_string(),
+            ));
+        }
+        (Some(after), None) => (decode_offset(&secret, after)?, page_size, false),
+        (None, Some(before)) => {
+            let end = decode_offset(&secret, before)?;
+            (end.saturating_sub(page_size), end.min(page_size), true)
+        }
+        (None, None) => (0, page_size, false),
+    };
+    let db = &mut state.db;
+
+    let sql = format!(
+        "SELECT jokes_fts.rowid, \
+         snippet(jokes_fts, 0, '{MATCH_START}', '{MATCH_END}', '…', 16) \
+         FROM jokes_fts JOIN jokes ON jokes.id = jokes_fts.rowid \
+         WHERE jokes_fts MATCH ?1 AND NOT jokes.hidden AND jokes.deleted_at IS NULL \
+         ORDER BY bm25(jokes_fts), jokes_fts.rowid LIMIT ?2 OFFSET ?3"
+    );
+    let rows = toasty::sql::query(sql)
+        .bind(expression.clone())
+        .bind(to_i64(limit + 1))
+        .bind(to_i64(start))
+        .column_types([Type::I64, Type::String])
+        .exec(db)
+        .await?;
+    let more = rows.len() > limit;
+    let mut hits = Vec::with_capacity(limit);
+    for row in rows.into_iter().take(limit) {
+        let (id, snippet) = match row.as_record().map(|record| record.as_slice()) {
+            Some([Value::I64(id), Value::String(snippet)]) => (*id, snippet.clone()),
+            _ => {
+                return Err(AppError::Internal(format!(
+                    "unexpected search row: {row:?}"
+                )));
+            }
+        };
+        hits.push((id, highlight(&snippet)));
+    }
+
+    // The index and the table are updated in one transaction, so every hit
+    // has a row.
+    let ids: Vec<i64> = hits.iter().map(|(id, _)| *id).collect();
+    let mut jokes = Joke::filter(Joke::fields().id().in_list(ids))
+        .exec(db)
+        .await?;
+    let items: Vec<SearchHit> = hits
+        .into_iter()
+        .filter_map(|(id, snippet)| {
+            let index = jokes.iter().position(|joke| joke.id == id)?;
+            Some(SearchHit {
+                joke: jokes.swap_remove(index),
+                snippet,
+            })
+        })
+        .collect();
+
+    let end = start + items.len();
+    let cursor = if more || (backward && !items.is_empty()) {
+        Some(encode_offset(&secret, end)?)
+    } else {
+        None
+    };
+    let prev_cursor = if start > 0 {
+        Some(encode_offset(&secret, start)?)
+    } else {
+        None
+    };
+    let total = if params.include_total {
+        let rows = toasty::sql::query(
+            "SELECT count(*) FROM jokes_fts JOIN jokes ON jokes.id = jokes_fts.rowid \
+             WHERE jokes_fts MATCH ?1 AND NOT jokes.hidden AND jokes.deleted_at IS NULL",
+        )
+        .bind(expression)
+        .column_types([Type::I64])
+        .exec(db)
+        .await?;
+        match rows
+            .first()
+            .and_then(Value::as_record)
+            .map(|r| r.as_slice())
+        {
+            Some([Value::I64(count)]) => Some(u64::try_from(*count).unwrap_or_default()),
+            _ => return Err(AppError::Internal("unexpected search count".to_string())),
+        }
+    } else {
+        None
+    };
+    Ok(SerializablePage {
+        items,
+        cursor,
+        prev_cursor,
+        total,
+    })
+}
+
+/// Quote every whitespace-separated term so user input is never parsed as
+/// FTS5 query syntax; the terms are implicitly AND-ed.
+fn match_expression(terms: &str) -> Option<String> {
+    let quoted: Vec<String> = terms
+        .split_whitespace()
+        .map(|term| format!("\"{}\"", term.replace('"', "\"\"")))
+        .collect();
+    (!quoted.is_empty()).then(|| quoted.join(" "))
+}
+
+fn highlight(snippet: &str) -> String {
+    let mut out = String::with_capacity(snippet.len());
+    for c in snippet.chars() {
+        match c {
+            MATCH_START => out.push_str("<mark>"),
+            MATCH_END => out.push_str("</mark>"),
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+fn to_i64(n: usize) -> i64 {
+    i64::try_from(n).unwrap_or(i64::MAX)
+}
+
+fn encode_offset(secret: &str, offset: usize) -> Result<String, AppError> {
+    cursor::encode(secret, &Value::I64(to_i64(offset)))
+}
+
+fn decode_offset(secret: &str, token: &str) -> Result<usize, AppError> {
+    match cursor::decode(secret, token)?
+        .as_record()
+        .map(|r| r.as_slice())
+    {
+        Some([Value::I64(offset)]) => usize::try_from(*offset)
+            .map_err(|_| AppError::BadRequest("Invalid pagination cursor".to_string())),
+        _ => Err(AppError::BadRequest(
+            "Invalid pagination cursor".to_string(),
+        )),
+    }
+}
@@ -1,6 +1,25 @@
+use std::sync::Arc;
+
+use crate::config::Config;
+
 /// Application state shared across all handlers.
-/// Contains the Toasty database handle.
+/// Contains the Toasty database handle and the runtime configuration.
 #[derive(Clone)]
 pub struct AppState {
     pub db: toasty::Db,
+    pub config: Arc<Config>,
+}
+
+impl AppState {
+    /// Create a state with the default configuration.
+    pub fn new(db: toasty::Db) -> Self {
+        Self::with_config(db, Config::default())
+    }
+
+    pub fn with_config(db: toasty::Db, config: Config) -> Self {
+        Self {
+            db,
+            config: Arc::new(config),
+        }
+    }
 }
@@ -1,19 +1,44 @@
 use axum::{body::Body, http::Request, response::Response};
-use axum_everyone::{AppState, Joke, JokeRequest, SerializablePage, User, UserRequest, create_app};
+use axum_everyone::{
+    AppState, Comment, DailyJoke, Joke, JokeRequest, SerializablePage, User, UserRequest, auth,
+    config::Config, create_app, migrate, purge, schemas::user::Role, search,
+};
 use http_body_util::BodyExt;
 use tower::ServiceExt;
 
 /// Create a fresh test database with schema applied.
 async fn create_test_db() -> toasty::Db {
-    let db = toasty::Db::builder()
+    let mut db = toasty::Db::builder()
         .models(toasty::models!(crate::*))
         .connect("sqlite::memory:")
         .await
         .unwrap();
     db.push_schema().await.unwrap();
+    search::install(&mut db).await.unwrap();
     db
 }
 
+/// Create an app backed by a fresh database, plus an operator API key.
+async fn setup() -> (axum::Router, String) {
+    let (app, _, key) = setup_with_db().await;
+    (app, key)
+}
+
+/// Like [`setup`], but also hands back the database so tests can issue more keys.
+async fn setup_with_db() -> (axum::Router, toasty::Db, String) {
+    let mut db = create_test_db().await;
+    let (_, key) = auth::issue_api_key(&mut db, "test", None).await.unwrap();
+    (create_app(AppState::new(db.clone())), db, key)
+}
+
+/// Issue an API key bound to `user_id`.
+async fn user_key(db: &mut toasty::Db, user_id: i64) -> String {
+    let (_, key) = auth::issue_api_key(db, "user", Some(user_id))
+        .await
+        .unwrap();
+    key
+}
+
 /// Helper to extract the JSON body from a response.
 async fn json_body<T: for<'de> serde::Deserialize<'de>>(response: Response) -> T {
     let bytes = response.into_body().collect().await.unwrap().to_bytes();
@@ -21,10 +46,11 @@ async fn json_body<T: for<'de> serde::Deserialize<'de>>(response: Response) -> T
 }
 
 /// Create a user via the API and return it.
-async fn create_user(app: axum::Router, name: &str, email: &str) -> User {
+async fn create_user(app: axum::Router, key: &str, name: &str, email: &str) -> User {
     let create_req = Request::builder()
         .method("POST")
         .uri("/users")
+        .header("authorization", format!("Bearer {key}"))
         .header("content-type", "application/json")
         .body(Body::from(
             serde_json::to_string(&UserRequest {
@@ -41,14 +67,16 @@ async fn create_user(app: axum::Router, name: &str, email: &str) -> User {
 }
 
 /// Create a joke for a user via the API and return it.
-async fn create_joke(app: axum::Router, user_id: i64, content: &str) -> Joke {
+async fn create_joke(app: axum::Router, key: &str, user_id: i64, content: &str) -> Joke {
     let create_req = Request::builder()
         .method("POST")
         .uri(format!("/users/{user_id}/jokes"))
+        .header("authorization", format!("Bearer {key}"))
         .header("content-type", "application/json")
         .body(Body::from(
             serde_json::to_string(&JokeRequest {
                 content: content.to_string(),
+                tags: None,
             })
             .unwrap(),
         ))
@@ -61,9 +89,7 @@ async fn create_joke(app: axum::Router, user_id: i64, content: &str) -> Joke {
 
 #[tokio::test]
 async fn test_health_check() {
-    let db = create_test_db().await;
-    let state = AppState { db };
-    let app = create_app(state);
+    let (app, _) = setup().await;
 
     let response = app
         .oneshot(
@@ -82,11 +108,9 @@ async fn test_health_check() {
 
 #[tokio::test]
 async fn test_create_and_get_user() {
-    let db = create_test_db().await;
-    let state = AppState { db };
-    let app = create_app(state);
+    let (app, key) = setup().await;
 
-    let user = create_user(app.clone(), "Alice", "alice@example.com").await;
+    let user = create_user(app.clone(), &key, "Alice", "alice@example.com").await;
     assert_eq!(user.name, "Alice");
     assert_eq!(user.email, "alice@example.com");
     let user_id = user.id;
@@ -106,12 +130,16 @@ async fn test_create_and_get_user() {
 
 #[tokio::test]
 async fn test_create_and_get_joke() {
-    let db = create_test_db().await;
-    let state = AppState { db };
-    let app = create_app(state);
-
-    let user = create_user(app.clone(), "Bob", "bob@example.com").await;
-    let joke = create_joke(app.clone(), user.id, "Why did the chicken cross the road?").await;
+    let (app, key) = setup().await;
+
+    let user = create_user(app.clone(), &key, "Bob", "bob@example.com").await;
+    let joke = create_joke(
+        app.clone(),
+        &key,
+        user.id,
+        "Why did the chicken cross the road?",
+    )
+    .await;
     assert_eq!(joke.content, "Why did the chicken cross the road?");
     assert_eq!(joke.user_id, user.id);
     let joke_id = joke.id;
@@ -131,14 +159,12 @@ async fn test_create_and_get_joke() {
 
 #[tokio::test]
 async fn test_user_jokes_relation() {
-    let db = create_test_db().await;
-    let state = AppState { db };
-    let app = create_app(state);
+    let (app, key) = setup().await;
 
-    let user = create_user(app.clone(), "Carol", "carol@example.com").await;
-    create_joke(app.clone(), user.id, "Joke 1").await;
-    create_joke(app.clone(), user.id, "Joke 2").await;
-    create_joke(app.clone(), user.id, "Joke 3").await;
+    let user = create_user(app.clone(), &key, "Carol", "carol@example.com").await;
+    create_joke(app.clone(), &key, user.id, "Joke 1").await;
+    create_joke(app.clone(), &key, user.id, "Joke 2").await;
+    create_joke(app.clone(), &key, user.id, "Joke 3").await;
 
     let get_req = Request::builder()
         .uri(format!("/users/{}/jokes", user.id))
@@ -148,28 +174,29 @@ async fn test_user_jokes_relation() {
     let response = app.oneshot(get_req).await.unwrap();
     assert_eq!(response.status(), axum::http::StatusCode::OK);
 
-    let jokes: Vec<Joke> = json_body(response).await;
-    assert_eq!(jokes.len(), 3);
-    assert!(jokes.iter().all(|j| j.user_id == user.id));
+    let page: SerializablePage<Joke> = json_body(response).await;
+    assert_eq!(page.items.len(), 3);
+    assert!(page.items.iter().all(|j| j.user_id == user.id));
+    assert!(page.cursor.is_none());
 }
 
 #[tokio::test]
 async fn test_update_joke() {
-    let db = create_test_db().await;
-    let state = AppState { db };
-    let app = create_app(state);
+    let (app, key) = setup().await;
 
-    let user = create_user(app.clone(), "Dave", "dave@example.com").await;
-    let joke = create_joke(app.clone(), user.id, "Old joke").await;
+    let user = create_user(app.clone(), &key, "Dave", "dave@example.com").await;
+    let joke = create_joke(app.clone(), &key, user.id, "Old joke").await;
     let joke_id = joke.id;
 
     let update_req = Request::builder()
         .method("PUT")
         .uri(format!("/joke/{joke_id}"))
+        .header("authorization", format!("Bearer {key}"))
         .header("content-type", "application/json")
         .body(Body::from(
             serde_json::to_string(&JokeRequest {
                 content: "Updated joke".to_string(),
+                tags: None,
             })
             .unwrap(),
         ))
@@ -177,21 +204,66 @@ async fn test_update_joke() {
 
     let response = app.oneshot(update_req).await.unwrap();
     assert_eq!(response.status(), axum::http::StatusCode::OK);
+
+    let updated: Joke = json_body(response).await;
+    assert_eq!(updated.id, joke_id);
+    assert_eq!(updated.content, "Updated joke");
+}
+
+#[tokio::test]
+async fn test_update_joke_prefer_minimal() {
+    let (app, key) = setup().await;
+
+    let user = create_user(app.clone(), &key, "Dave", "dave@example.com").await;
+    let joke = create_joke(app.clone(), &key, user.id, "Old joke").await;
+    let joke_id = joke.id;
+
+    let update_req = Request::builder()
+        .method("PUT")
+        .uri(format!("/joke/{joke_id}"))
+        .header("authorization", format!("Bearer {key}"))
+        .header("content-type", "application/json")
+        .header("prefer", "return=minimal")
+        .body(Body::from(
+            serde_json::to_string(&JokeRequest {
+                content: "Updated joke".to_string(),
+                tags: None,
+            })
+            .unwrap(),
+        ))
+        .unwrap();
+
+    let response = app.clone().oneshot(update_req).await.unwrap();
+    assert_eq!(response.status(), axum::http::StatusCode::NO_CONTENT);
+    assert_eq!(
+        response.headers().get("preference-applied").unwrap(),
+        "return=minimal"
+    );
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    assert!(body.is_empty());
+
+    let get_req = Request::builder()
+        .uri(format!("/joke/{joke_id}"))
+        .body(Body::empty())
+        .unwrap();
+
+    let response = app.oneshot(get_req).await.unwrap();
+    let retrieved: Joke = json_body(response).await;
+    assert_eq!(retrieved.content, "Updated joke");
 }
 
 #[tokio::test]
 async fn test_delete_joke() {
-    let db = create_test_db().await;
-    let state = AppState { db };
-    let app = create_app(state);
+    let (app, key) = setup().await;
 
-    let user = create_user(app.clone(), "Eve", "eve@example.com").await;
-    let joke = create_joke(app.clone(), user.id, "Delete me").await;
+    let user = create_user(app.clone(), &key, "Eve", "eve@example.com").await;
+    let joke = create_joke(app.clone(), &key, user.id, "Delete me").await;
     let joke_id = joke.id;
 
     let delete_req = Request::builder()
         .method("DELETE")
         .uri(format!("/joke/{joke_id}"))
+        .header("authorization", format!("Bearer {key}"))
         .body(Body::empty())
         .unwrap();
 
@@ -209,13 +281,11 @@ async fn test_delete_joke() {
 
 #[tokio::test]
 async fn test_get_all_jokes() {
-    let db = create_test_db().await;
-    let state = AppState { db };
-    let app = create_app(state);
+    let (app, key) = setup().await;
 
-    let user = create_user(app.clone(), "Frank", "frank@example.com").await;
+    let user = create_user(app.clone(), &key, "Frank", "frank@example.com").await;
     for i in 0..3 {
-        create_joke(app.clone(), user.id, &format!("Joke {i}")).await;
+        create_joke(app.clone(), &key, user.id, &format!("Joke {i}")).await;
     }
 
     let get_req = Request::builder()
@@ -232,19 +302,19 @@ async fn test_get_all_jokes() {
 
 #[tokio::test]
 async fn test_validation_empty_content() {
-    let db = create_test_db().await;
-    let state = AppState { db };
-    let app = create_app(state);
+    let (app, key) = setup().await;
 
-    let user = create_user(app.clone(), "Grace", "grace@example.com").await;
+    let user = create_user(app.clone(), &key, "Grace", "grace@example.com").await;
 
     let create_req = Request::builder()
         .method("POST")
         .uri(format!("/users/{}/jokes", user.id))
+        .header("authorization", format!("Bearer {key}"))
         .header("content-type", "application/json")
         .body(Body::from(
             serde_json::to_string(&JokeRequest {
                 content: String::new(),
+                tags: None,
             })
             .unwrap(),
         ))
@@ -256,18 +326,17 @@ async fn test_validation_empty_content() {
 
 #[tokio::test]
 async fn test_delete_all_jokes() {
-    let db = create_test_db().await;
-    let state = AppState { db };
-    let app = create_app(state);
+    let (app, key) = setup().await;
 
-    let user = create_user(app.clone(), "Heidi", "heidi@example.com").await;
+    let user = create_user(app.clone(), &key, "Heidi", "heidi@example.com").await;
     for _ in 0..2 {
-        create_joke(app.clone(), user.id, "Joke").await;
+        create_joke(app.clone(), &key, user.id, "Joke").await;
     }
 
     let delete_req = Request::builder()
         .method("DELETE")
         .uri("/jokes")
+        .header("authorization", format!("Bearer {key}"))
         .body(Body::empty())
         .unwrap();
 
@@ -286,17 +355,17 @@ async fn test_delete_all_jokes() {
 
 #[tokio::test]
 async fn test_add_joke_nonexistent_user() {
-    let db = create_test_db().await;
-    let state = AppState { db };
-    let app = create_app(state);
+    let (app, key) = setup().await;
 
     let create_req = Request::builder()
         .method("POST")
         .uri("/users/9999/jokes")
+        .header("authorization", format!("Bearer {key}"))
         .header("content-type", "application/json")
         .body(Body::from(
             serde_json::to_string(&JokeRequest {
                 content: "Orphan joke".to_string(),
+                tags: None,
             })
             .unwrap(),
         ))
@@ -308,12 +377,10 @@ async fn test_add_joke_nonexistent_user() {
 
 #[tokio::test]
 async fn test_get_all_users() {
-    let db = create_test_db().await;
-    let state = AppState { db };
-    let app = create_app(state);
+    let (app, key) = setup().await;
 
-    create_user(app.clone(), "Ivan", "ivan@example.com").await;
-    create_user(app.clone(), "Judy", "judy@example.com").await;
+    create_user(app.clone(), &key, "Ivan", "ivan@example.com").await;
+    create_user(app.clone(), &key, "Judy", "judy@example.com").await;
 
     let get_req = Request::builder()
         .uri("/users")
@@ -323,22 +390,21 @@ async fn test_get_all_users() {
     let response = app.oneshot(get_req).await.unwrap();
     assert_eq!(response.status(), axum::http::StatusCode::OK);
 
-    let users: Vec<User> = json_body(response).await;
-    assert_eq!(users.len(), 2);
+    let page: SerializablePage<User> = json_body(response).await;
+    assert_eq!(page.items.len(), 2);
 }
 
 #[tokio::test]
 async fn test_delete_user() {
-    let db = create_test_db().await;
-    let state = AppState { db };
-    let app = create_app(state);
+    let (app, key) = setup().await;
 
-    let user = create_user(app.clone(), "Karl", "karl@example.com").await;
+    let user = create_user(app.clone(), &key, "Karl", "karl@example.com").await;
     let user_id = user.id;
 
     let delete_req = Request::builder()
         .method("DELETE")
         .uri(format!("/user/{user_id}"))
+        .header("authorization", format!("Bearer {key}"))
         .body(Body::empty())
         .unwrap();
 
@@ -356,9 +422,7 @@ async fn test_delete_user() {
 
 #[tokio::test]
 async fn test_get_nonexistent_user() {
-    let db = create_test_db().await;
-    let state = AppState { db };
-    let app = create_app(state);
+    let (app, _) = setup().await;
 
     let response = app
         .oneshot(
@@ -375,16 +439,15 @@ async fn test_get_nonexistent_user() {
 
 #[tokio::test]
 async fn test_update_user() {
-    let db = create_test_db().await;
-    let state = AppState { db };
-    let app = create_app(state);
+    let (app, key) = setup().await;
 
-    let user = create_user(app.clone(), "Alice", "alice@example.com").await;
+    let user = create_user(app.clone(), &key, "Alice", "alice@example.com").await;
     let user_id = user.id;
 
     let update_req = Request::builder()
         .method("PUT")
         .uri(format!("/user/{user_id}"))
+        .header("authorization", format!("Bearer {key}"))
         .header("content-type", "application/json")
         .body(Body::from(
             serde_json::to_string(&UserRequest {
@@ -398,6 +461,10 @@ async fn test_update_user() {
     let response = app.clone().oneshot(update_req).await.unwrap();
     assert_eq!(response.status(), axum::http::StatusCode::OK);
 
+    let updated: User = json_body(response).await;
+    assert_eq!(updated.name, "Alice Updated");
+    assert_eq!(updated.email, "alice.updated@example.com");
+
     let get_req = Request::builder()
         .uri(format!("/user/{user_id}"))
         .body(Body::empty())
@@ -413,13 +480,12 @@ async fn test_update_user() {
 
 #[tokio::test]
 async fn test_update_nonexistent_user() {
-    let db = create_test_db().await;
-    let state = AppState { db };
-    let app = create_app(state);
+    let (app, key) = setup().await;
 
     let update_req = Request::builder()
         .method("PUT")
         .uri("/user/9999")
+        .header("authorization", format!("Bearer {key}"))
         .header("content-type", "application/json")
         .body(Body::from(
             serde_json::to_string(&UserRequest {
@@ -431,22 +497,35 @@ async fn test_update_nonexistent_user() {
         .unwrap();
 
     let response = app.oneshot(update_req).await.unwrap();
-    // toasty::update! succeeds even if no matching record exists
-    assert_eq!(response.status(), axum::http::StatusCode::OK);
+    assert_eq!(response.status(), axum::http::StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_delete_nonexistent_user() {
+    let (app, key) = setup().await;
+
+    let delete_req = Request::builder()
+        .method("DELETE")
+        .uri("/user/9999")
+        .header("authorization", format!("Bearer {key}"))
+        .body(Body::empty())
+        .unwrap();
+
+    let response = app.oneshot(delete_req).await.unwrap();
+    assert_eq!(response.status(), axum::http::StatusCode::NOT_FOUND);
 }
 
 #[tokio::test]
 async fn test_delete_all_users() {
-    let db = create_test_db().await;
-    let state = AppState { db };
-    let app = create_app(state);
+    let (app, key) = setup().await;
 
-    create_user(app.clone(), "Liam", "liam@example.com").await;
-    create_user(app.clone(), "Mia", "mia@example.com").await;
+    create_user(app.clone(), &key, "Liam", "liam@example.com").await;
+    create_user(app.clone(), &key, "Mia", "mia@example.com").await;
 
     let delete_req = Request::builder()
         .method("DELETE")
         .uri("/users")
+        .header("authorization", format!("Bearer {key}"))
         .body(Body::empty())
         .unwrap();
 
@@ -459,19 +538,18 @@ async fn test_delete_all_users() {
         .unwrap();
 
     let response = app.oneshot(get_req).await.unwrap();
-    let users: Vec<User> = json_body(response).await;
-    assert!(users.is_empty());
+    let page: SerializablePage<User> = json_body(response).await;
+    assert!(page.items.is_empty());
 }
 
 #[tokio::test]
 async fn test_validation_user_empty_name() {
-    let db = create_test_db().await;
-    let state = AppState { db };
-    let app = create_app(state);
+    let (app, key) = setup().await;
 
     let create_req = Request::builder()
         .method("POST")
         .uri("/users")
+        .header("authorization", format!("Bearer {key}"))
         .header("content-type", "application/json")
         .body(Body::from(
             serde_json::to_string(&UserRequest {
@@ -488,13 +566,12 @@ async fn test_validation_user_empty_name() {
 
 #[tokio::test]
 async fn test_validation_user_invalid_email() {
-    let db = create_test_db().await;
-    let state = AppState { db };
-    let app = create_app(state);
+    let (app, key) = setup().await;
 
     let create_req = Request::builder()
         .method("POST")
         .uri("/users")
+        .header("authorization", format!("Bearer {key}"))
         .header("content-type", "application/json")
         .body(Body::from(
             serde_json::to_string(&UserRequest {
@@ -511,11 +588,9 @@ async fn test_validation_user_invalid_email() {
 
 #[tokio::test]
 async fn test_get_user_jokes_empty() {
-    let db = create_test_db().await;
-    let state = AppState { db };
-    let app = create_app(state);
+    let (app, key) = setup().await;
 
-    let user = create_user(app.clone(), "Olivia", "olivia@example.com").await;
+    let user = create_user(app.clone(), &key, "Olivia", "olivia@example.com").await;
 
     let get_req = Request::builder()
         .uri(format!("/users/{}/jokes", user.id))
@@ -525,15 +600,13 @@ async fn test_get_user_jokes_empty() {
     let response = app.oneshot(get_req).await.unwrap();
     assert_eq!(response.status(), axum::http::StatusCode::OK);
 
-    let jokes: Vec<Joke> = json_body(response).await;
-    assert!(jokes.is_empty());
+    let page: SerializablePage<Joke> = json_body(response).await;
+    assert!(page.items.is_empty());
 }
 
 #[tokio::test]
 async fn test_get_nonexistent_joke() {
-    let db = create_test_db().await;
-    let state = AppState { db };
-    let app = create_app(state);
+    let (app, _) = setup().await;
 
     let response = app
         .oneshot(
@@ -550,52 +623,48 @@ async fn test_get_nonexistent_joke() {
 
 #[tokio::test]
 async fn test_update_nonexistent_joke() {
-    let db = create_test_db().await;
-    let state = AppState { db };
-    let app = create_app(state);
+    let (app, key) = setup().await;
 
     let update_req = Request::builder()
         .method("PUT")
         .uri("/joke/9999")
+        .header("authorization", format!("Bearer {key}"))
         .header("content-type", "application/json")
         .body(Body::from(
             serde_json::to_string(&JokeRequest {
                 content: "Ghost joke".to_string(),
+                tags: None,
             })
             .unwrap(),
         ))
         .unwrap();
 
     let response = app.oneshot(update_req).await.unwrap();
-    // toasty::update! succeeds even if no matching record exists
-    assert_eq!(response.status(), axum::http::StatusCode::OK);
+    assert_eq!(response.status(), axum::http::StatusCode::NOT_FOUND);
 }
 
 #[tokio::test]
 async fn test_delete_nonexistent_joke() {
-    let db = create_test_db().await;
-    let state = AppState { db };
-    let app = create_app(state);
+    let (app, key) = setup().await;
 
     let delete_req = Request::builder()
         .method("DELETE")
         .uri("/joke/9999")
+        .header("authorization", format!("Bearer {key}"))
         .body(Body::empty())
         .unwrap();
 
     let response = app.oneshot(delete_req).await.unwrap();
-    assert_eq!(response.status(), axum::http::StatusCode::OK);
+    assert_eq!(response.status(), axum::http::StatusCode::NOT_FOUND);
 }
 
 #[tokio::test]
 async fn test_paginate_jokes() {
-    let db = create_test_db().await;
-    let state = AppState { db };
-    let app = create_app(state);
+    let (app, key) = setup().await;
 
-    let user = create_user(app.clone(), "Quinn", "quinn@example.com").await;
+    let user = create_user(app.clone(), &key, "Quinn", "quinn@example.com").await;
     for i in 0..5 {
-        create_joke(app.clone(), user.id, &format!("Joke {i}")).await;
+        create_joke(app.clone(), &key, user.id, &format!("Joke {i}")).await;
     }
 
     let get_req = Request::builder()
@@ -639,3 +708,2517 @@ async fn test_paginate_jokes() {
     assert_eq!(page.items.len(), 1);
     assert!(page.cursor.is_none(), "expected no cursor on the last page");
 }
+
+#[tokio::test]
+async fn test_mutation_requires_api_key() {
+    let (app, _) = setup().await;
+
+    let create_req = Request::builder()
+        .method("POST")
+        .uri("/users")
+        .header("content-type", "application/json")
+        .body(Body::from(
+            serde_json::to_string(&UserRequest {
+                name: "Mallory".to_string(),
+                email: "mallory@example.com".to_string(),
+            })
+            .unwrap(),
+        ))
+        .unwrap();
+
+    let response = app.clone().oneshot(create_req).await.unwrap();
+    assert_eq!(response.status(), axum::http::StatusCode::UNAUTHORIZED);
+    assert_eq!(response.headers()["www-authenticate"], "Bearer");
+
+    let delete_req = Request::builder()
+        .method("DELETE")
+        .uri("/users")
+        .header("authorization", "Bearer not-a-real-key")
+        .body(Body::empty())
+        .unwrap();
+
+    let response = app.oneshot(delete_req).await.unwrap();
+    assert_eq!(response.status(), axum::http::StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn test_private_reads_require_api_key() {
+    let mut db = create_test_db().await;
+    let (_, key) = auth::issue_api_key(&mut db, "test", None).await.unwrap();
+    let config = Config {
+        public_reads: false,
+        ..Config::default()
+    };
+    let app = create_app(AppState::with_config(db, config));
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/jokes")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), axum::http::StatusCode::UNAUTHORIZED);
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/jokes")
+                .header("authorization", format!("Bearer {key}"))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), axum::http::StatusCode::OK);
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/health")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), axum::http::StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_cannot_modify_other_users_jokes() {
+    let (app, mut db, key) = setup_with_db().await;
+
+    let alice = create_user(app.clone(), &key, "Alice", "alice@example.com").await;
+    let bob = create_user(app.clone(), &key, "Bob", "bob@example.com").await;
+    let alice_key = user_key(&mut db, alice.id).await;
+    let joke = create_joke(app.clone(), &alice_key, alice.id, "Alice's joke").await;
+    let bob_key = user_key(&mut db, bob.id).await;
+
+    let update_req = Request::builder()
+        .method("PUT")
+        .uri(format!("/joke/{}", joke.id))
+        .header("authorization", format!("Bearer {bob_key}"))
+        .header("content-type", "application/json")
+        .body(Body::from(
+            serde_json::to_string(&JokeRequest {
+                content: "Hijacked".to_string(),
+                tags: None,
+            })
+            .unwrap(),
+        ))
+        .unwrap();
+    let response = app.clone().oneshot(update_req).await.unwrap();
+    assert_eq!(response.status(), axum::http::StatusCode::FORBIDDEN);
+
+    let delete_req = Request::builder()
+        .method("DELETE")
+        .uri(format!("/joke/{}", joke.id))
+        .header("authorization", format!("Bearer {bob_key}"))
+        .body(Body::empty())
+        .unwrap();
+    let response = app.clone().oneshot(delete_req).await.unwrap();
+    assert_eq!(response.status(), axum::http::StatusCode::FORBIDDEN);
+
+    let create_req = Request::builder()
+        .method("POST")
+        .uri(format!("/users/{}/jokes", alice.id))
+        .header("authorization", format!("Bearer {bob_key}"))
+        .header("content-type", "application/json")
+        .body(Body::from(
+            serde_json::to_string(&JokeRequest {
+                content: "Impersonated".to_string(),
+                tags: None,
+            })
+            .unwrap(),
+        ))
+        .unwrap();
+    let response = app.clone().oneshot(create_req).await.unwrap();
+    assert_eq!(response.status(), axum::http::StatusCode::FORBIDDEN);
+
+    let get_req = Request::builder()
+        .uri(format!("/joke/{}", joke.id))
+        .body(Body::empty())
+        .unwrap();
+    let response = app.oneshot(get_req).await.unwrap();
+    let retrieved: Joke = json_body(response).await;
+    assert_eq!(retrieved.content, "Alice's joke");
+}
+
+#[tokio::test]
+async fn test_cannot_modify_other_users() {
+    let (app, key) = setup().await;
+    let admin = create_user(app.clone(), &key, "Admin", "admin@example.com").await;
+
+    // Anyone can sign up, so a self-registered user must not reach other accounts.
+    let response = post_json(
+        app.clone(),
+        "/auth/register",
+        serde_json::json!({"name": "Mallory", "email": "mallory@example.com", "password": "correct horse"}),
+    )
+    .await;
+    assert_eq!(response.status(), axum::http::StatusCode::CREATED);
+    let mallory: User = json_body(response).await;
+    let response = post_json(
+        app.clone(),
+        "/auth/login",
+        serde_json::json!({"email": "mallory@example.com", "password": "correct horse"}),
+    )
+    .await;
+    let tokens: serde_json::Value = json_body(response).await;
+    let token = tokens["access_token"].as_str().unwrap().to_string();
+
+    let send = |method: &'static str, uri: String, body: Body| {
+        let app = app.clone();
+        let token = token.clone();
+        async move {
+            let req = Request::builder()
+                .method(method)
+                .uri(uri)
+                .header("authorization", format!("Bearer {token}"))
+                .header("content-type", "application/json")
+                .body(body)
+                .unwrap();
+            app.oneshot(req).await.unwrap().status()
+        }
+    };
+    let takeover = || {
+        Body::from(
+            serde_json::to_string(&UserRequest {
+                name: "Mallory".to_string(),
+                email: "mallory@example.net".to_string(),
+            })
+            .unwrap(),
+        )
+    };
+
+    let admin_uri = format!("/user/{}", admin.id);
+    assert_eq!(
+        send("PUT", admin_uri.clone(), takeover()).await,
+        axum::http::StatusCode::FORBIDDEN
+    );
+    assert_eq!(
+        send("PATCH", admin_uri.clone(), takeover()).await,
+        axum::http::StatusCode::FORBIDDEN
+    );
+    assert_eq!(
+        send("DELETE", admin_uri.clone(), Body::empty()).await,
+        axum::http::StatusCode::FORBIDDEN
+    );
+    assert_eq!(
+        send("POST", "/users".to_string(), takeover()).await,
+        axum::http::StatusCode::FORBIDDEN
+    );
+
+    let get_req = Request::builder()
+        .uri(admin_uri)
+        .body(Body::empty())
+        .unwrap();
+    let response = app.clone().oneshot(get_req).await.unwrap();
+    let retrieved: User = json_body(response).await;
+    assert_eq!(retrieved.email, "admin@example.com");
+
+    // Their own account is still theirs to change.
+    assert_eq!(
+        send("PUT", format!("/user/{}", mallory.id), takeover()).await,
+        axum::http::StatusCode::OK
+    );
+}
+
+#[tokio::test]
+async fn test_owner_can_modify_own_joke() {
+    let (app, mut db, key) = setup_with_db().await;
+
+    let alice = create_user(app.clone(), &key, "Alice", "alice@example.com").await;
+    let alice_key = user_key(&mut db, alice.id).await;
+    let joke = create_joke(app.clone(), &alice_key, alice.id, "Draft").await;
+
+    let update_req = Request::builder()
+        .method("PUT")
+        .uri(format!("/joke/{}", joke.id))
+        .header("authorization", format!("Bearer {alice_key}"))
+        .header("content-type", "application/json")
+        .body(Body::from(
+            serde_json::to_string(&JokeRequest {
+                content: "Final".to_string(),
+                tags: None,
+            })
+            .unwrap(),
+        ))
+        .unwrap();
+    let response = app.clone().oneshot(update_req).await.unwrap();
+    assert_eq!(response.status(), axum::http::StatusCode::OK);
+
+    let delete_req = Request::builder()
+        .method("DELETE")
+        .uri(format!("/joke/{}", joke.id))
+        .header("authorization", format!("Bearer {alice_key}"))
+        .body(Body::empty())
+        .unwrap();
+    let response = app.oneshot(delete_req).await.unwrap();
+    assert_eq!(response.status(), axum::http::StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_bulk_deletes_require_admin() {
+    let (app, mut db, key) = setup_with_db().await;
+
+    let alice = create_user(app.clone(), &key, "Alice", "alice@example.com").await;
+    let alice_key = user_key(&mut db, alice.id).await;
+
+    for uri in ["/users", "/jokes"] {
+        let delete_req = Request::builder()
+            .method("DELETE")
+            .uri(uri)
+            .header("authorization", format!("Bearer {alice_key}"))
+            .body(Body::empty())
+            .unwrap();
+        let response = app.clone().oneshot(delete_req).await.unwrap();
+        assert_eq!(response.status(), axum::http::StatusCode::FORBIDDEN);
+    }
+}
+
+#[tokio::test]
+async fn test_roles_grant_moderation() {
+    let (app, mut db, key) = setup_with_db().await;
+
+    let alice = create_user(app.clone(), &key, "Alice", "alice@example.com").await;
+    let bob = create_user(app.clone(), &key, "Bob", "bob@example.com").await;
+    assert_eq!(bob.role, Role::User);
+    let alice_key = user_key(&mut db, alice.id).await;
+    let bob_key = user_key(&mut db, bob.id).await;
+    let joke = create_joke(app.clone(), &alice_key, alice.id, "Questionable").await;
+
+    let role_req = |key: &str| {
+        Request::builder()
+            .method("PUT")
+            .uri(format!("/user/{}/role", bob.id))
+            .header("authorization", format!("Bearer {key}"))
+            .header("content-type", "application/json")
+            .body(Body::from(r#"{"role":"moderator"}"#))
+            .unwrap()
+    };
+
+    let response = app.clone().oneshot(role_req(&alice_key)).await.unwrap();
+    assert_eq!(response.status(), axum::http::StatusCode::FORBIDDEN);
+
+    let response = app.clone().oneshot(role_req(&key)).await.unwrap();
+    assert_eq!(response.status(), axum::http::StatusCode::OK);
+
+    let update_req = Request::builder()
+        .method("PUT")
+        .uri(format!("/joke/{}", joke.id))
+        .header("authorization", format!("Bearer {bob_key}"))
+        .header("content-type", "application/json")
+        .body(Body::from(
+            serde_json::to_string(&JokeRequest {
+                content: "Moderated".to_string(),
+                tags: None,
+            })
+            .unwrap(),
+        ))
+        .unwrap();
+    let response = app.clone().oneshot(update_req).await.unwrap();
+    assert_eq!(response.status(), axum::http::StatusCode::OK);
+
+    let delete_req = Request::builder()
+        .method("DELETE")
+        .uri("/jokes")
+        .header("authorization", format!("Bearer {bob_key}"))
+        .body(Body::empty())
+        .unwrap();
+    let response = app.oneshot(delete_req).await.unwrap();
+    assert_eq!(response.status(), axum::http::StatusCode::FORBIDDEN);
+}
+
+/// POST a JSON body without credentials and return the response.
+async fn post_json(app: axum::Router, uri: &str, body: serde_json::Value) -> Response {
+    let req = Request::builder()
+        .method("POST")
+        .uri(uri)
+        .header("content-type", "application/json")
+        .body(Body::from(body.to_string()))
+        .unwrap();
+    app.oneshot(req).await.unwrap()
+}
+
+#[tokio::test]
+async fn test_register_login_and_use_access_token() {
+    let (app, _) = setup().await;
+
+    let response = post_json(
+        app.clone(),
+        "/auth/register",
+        serde_json::json!({"name": "Rita", "email": "rita@example.com", "password": "correct horse"}),
+    )
+    .await;
+    assert_eq!(response.status(), axum::http::StatusCode::CREATED);
+    let body: serde_json::Value = json_body(response).await;
+    assert!(body.get("password_hash").is_none());
+    let user_id = body["id"].as_i64().unwrap();
+
+    let response = post_json(
+        app.clone(),
+        "/auth/login",
+        serde_json::json!({"email": "rita@example.com", "password": "wrong password"}),
+    )
+    .await;
+    assert_eq!(response.status(), axum::http::StatusCode::UNAUTHORIZED);
+    let response = post_json(
+        app.clone(),
+        "/auth/login",
+        serde_json::json!({"email": "nobody@example.com", "password": "correct horse"}),
+    )
+    .await;
+    assert_eq!(response.status(), axum::http::StatusCode::UNAUTHORIZED);
+
+    let response = post_json(
+        app.clone(),
+        "/auth/login",
+        serde_json::json!({"email": "rita@example.com", "password": "correct horse"}),
+    )
+    .await;
+    assert_eq!(response.status(), axum::http::StatusCode::OK);
+    let tokens: serde_json::Value = json_body(response).await;
+    let access_token = tokens["access_token"].as_str().unwrap();
+
+    let joke = create_joke(app.clone(), access_token, user_id, "Logged-in joke").await;
+    assert_eq!(joke.user_id, user_id);
+
+    // Refresh tokens are not accepted as access tokens.
+    let refresh_token = tokens["refresh_token"].as_str().unwrap();
+    let delete_req = Request::builder()
+        .method("DELETE")
+        .uri(format!("/joke/{}", joke.id))
+        .header("authorization", format!("Bearer {refresh_token}"))
+        .body(Body::empty())
+        .unwrap();
+    let response = app.oneshot(delete_req).await.unwrap();
+    assert_eq!(response.status(), axum::http::StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn test_refresh_token_rotation_and_reuse() {
+    let (app, _) = setup().await;
+
+    post_json(
+        app.clone(),
+        "/auth/register",
+        serde_json::json!({"name": "Sam", "email": "sam@example.com", "password": "hunter2hunter2"}),
+    )
+    .await;
+    let response = post_json(
+        app.clone(),
+        "/auth/login",
+        serde_json::json!({"email": "sam@example.com", "password": "hunter2hunter2"}),
+    )
+    .await;
+    let first: serde_json::Value = json_body(response).await;
+
+    let response = post_json(
+        app.clone(),
+        "/auth/refresh",
+        serde_json::json!({"refresh_token": first["refresh_token"]}),
+    )
+    .await;
+    assert_eq!(response.status(), axum::http::StatusCode::OK);
+    let second: serde_json::Value = json_body(response).await;
+    assert_ne!(first["refresh_token"], second["refresh_token"]);
+
+    // Replaying the rotated token is rejected and revokes the newer one too.
+    let response = post_json(
+        app.clone(),
+        "/auth/refresh",
+        serde_json::json!({"refresh_token": first["refresh_token"]}),
+    )
+    .await;
+    assert_eq!(response.status(), axum::http::StatusCode::UNAUTHORIZED);
+
+    let response = post_json(
+        app,
+        "/auth/refresh",
+        serde_json::json!({"refresh_token": second["refresh_token"]}),
+    )
+    .await;
+    assert_eq!(response.status(), axum::http::StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn test_errors_are_problem_json() {
+    let (app, key) = setup().await;
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/joke/9999")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), axum::http::StatusCode::NOT_FOUND);
+    assert_eq!(
+        response.headers()["content-type"],
+        "application/problem+json"
+    );
+    let problem: serde_json::Value = json_body(response).await;
+    assert_eq!(problem["type"], "about:blank");
+    assert_eq!(problem["title"], "Not Found");
+    assert_eq!(problem["status"], 404);
+
+    let create_req = Request::builder()
+        .method("POST")
+        .uri("/users")
+        .header("authorization", format!("Bearer {key}"))
+        .header("content-type", "application/json")
+        .body(Body::from(
+            serde_json::to_string(&UserRequest {
+                name: String::new(),
+                email: "not-an-email".to_string(),
+            })
+            .unwrap(),
+        ))
+        .unwrap();
+    let response = app.clone().oneshot(create_req).await.unwrap();
+    assert_eq!(response.status(), axum::http::StatusCode::BAD_REQUEST);
+    assert_eq!(
+        response.headers()["content-type"],
+        "application/problem+json"
+    );
+    let problem: serde_json::Value = json_body(response).await;
+    assert_eq!(problem["status"], 400);
+    assert_eq!(
+        problem["errors"]["email"][0],
+        "User email must be a valid email address"
+    );
+    assert_eq!(
+        problem["errors"]["name"][0],
+        "User name must be between 1 and 255 characters"
+    );
+
+    // Malformed path and query parameters are problems too.
+    for uri in [
+        "/joke/abc",
+        "/jokes/paginate?order=sideways",
+        "/jokes?created_after=garbage",
+        "/jokes/paginate?page_size=-1",
+        "/jokes?page_size=1&cursor=garbage",
+    ] {
+        let get_req = Request::builder().uri(uri).body(Body::empty()).unwrap();
+        let response = app.clone().oneshot(get_req).await.unwrap();
+        assert_eq!(
+            response.status(),
+            axum::http::StatusCode::BAD_REQUEST,
+            "{uri}"
+        );
+        assert_eq!(
+            response.headers()["content-type"],
+            "application/problem+json",
+            "{uri}"
+        );
+        let problem: serde_json::Value = json_body(response).await;
+        assert_eq!(problem["status"], 400);
+    }
+}
+
+#[tokio::test]
+async fn test_openapi_documents_problem_responses() {
+    let (app, _) = setup().await;
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/api-docs/openapi.json")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let doc: serde_json::Value = json_body(response).await;
+
+    let responses = &doc["paths"]["/joke/{id}"]["get"]["responses"];
+    for status in ["404", "500"] {
+        assert_eq!(
+            responses[status]["content"]["application/problem+json"]["schema"]["$ref"],
+            "#/components/schemas/ProblemDetails"
+        );
+    }
+    assert!(doc["components"]["securitySchemes"]["api_key"].is_object());
+
+    // The unpaginated listing documents only the parameters it honours.
+    let params = doc["paths"]["/jokes"]["get"]["parameters"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|param| param["name"].as_str().unwrap())
+        .collect::<Vec<_>>();
+    assert!(params.contains(&"sort"));
+    assert!(!params.contains(&"page_size"));
+    assert!(!params.contains(&"cursor"));
+}
+
+#[tokio::test]
+async fn test_add_user_duplicate_email_conflict() {
+    let (app, key) = setup().await;
+
+    create_user(app.clone(), &key, "Tina", "tina@example.com").await;
+
+    let create_req = Request::builder()
+        .method("POST")
+        .uri("/users")
+        .header("authorization", format!("Bearer {key}"))
+        .header("content-type", "application/json")
+        .body(Body::from(
+            serde_json::to_string(&UserRequest {
+                name: "Other Tina".to_string(),
+                email: "tina@example.com".to_string(),
+            })
+            .unwrap(),
+        ))
+        .unwrap();
+
+    let response = app.oneshot(create_req).await.unwrap();
+    assert_eq!(response.status(), axum::http::StatusCode::CONFLICT);
+    let problem: serde_json::Value = json_body(response).await;
+    assert_eq!(problem["detail"], "A record with this email already exists");
+}
+
+#[tokio::test]
+async fn test_update_user_duplicate_email_conflict() {
+    let (app, key) = setup().await;
+
+    create_user(app.clone(), &key, "Uma", "uma@example.com").await;
+    let victor = create_user(app.clone(), &key, "Victor", "victor@example.com").await;
+
+    let update_req = Request::builder()
+        .method("PUT")
+        .uri(format!("/user/{}", victor.id))
+        .header("authorization", format!("Bearer {key}"))
+        .header("content-type", "application/json")
+        .body(Body::from(
+            serde_json::to_string(&UserRequest {
+                name: "Victor".to_string(),
+                email: "uma@example.com".to_string(),
+            })
+            .unwrap(),
+        ))
+        .unwrap();
+
+    let response = app.oneshot(update_req).await.unwrap();
+    assert_eq!(response.status(), axum::http::StatusCode::CONFLICT);
+    let problem: serde_json::Value = json_body(response).await;
+    assert_eq!(problem["detail"], "A record with this email already exists");
+}
+
+/// Send a PATCH request with the given content type and JSON body.
+async fn patch_json(
+    app: axum::Router,
+    key: &str,
+    uri: &str,
+    content_type: &str,
+    body: serde_json::Value,
+) -> Response {
+    let req = Request::builder()
+        .method("PATCH")
+        .uri(uri)
+        .header("authorization", format!("Bearer {key}"))
+        .header("content-type", content_type)
+        .body(Body::from(body.to_string()))
+        .unwrap();
+    app.oneshot(req).await.unwrap()
+}
+
+#[tokio::test]
+async fn test_patch_user_updates_only_present_fields() {
+    let (app, key) = setup().await;
+
+    let user = create_user(app.clone(), &key, "Alice", "alice@example.com").await;
+    let uri = format!("/user/{}", user.id);
+
+    let response = patch_json(
+        app.clone(),
+        &key,
+        &uri,
+        "application/json",
+        serde_json::json!({ "name": "Alicia" }),
+    )
+    .await;
+    assert_eq!(response.status(), axum::http::StatusCode::OK);
+    let patched: User = json_body(response).await;
+    assert_eq!(patched.name, "Alicia");
+    assert_eq!(patched.email, "alice@example.com");
+
+    let response = patch_json(
+        app.clone(),
+        &key,
+        &uri,
+        "application/merge-patch+json",
+        serde_json::json!({ "email": "alicia@example.com", "name": null }),
+    )
+    .await;
+    assert_eq!(response.status(), axum::http::StatusCode::OK);
+    let patched: User = json_body(response).await;
+    assert_eq!(patched.name, "Alicia");
+    assert_eq!(patched.email, "alicia@example.com");
+
+    // Only present fields are validated.
+    let response = patch_json(
+        app.clone(),
+        &key,
+        &uri,
+        "application/json",
+        serde_json::json!({ "email": "not-an-email" }),
+    )
+    .await;
+    assert_eq!(response.status(), axum::http::StatusCode::BAD_REQUEST);
+    let problem: serde_json::Value = json_body(response).await;
+    assert!(problem["errors"]["email"].is_array());
+    assert!(problem["errors"].get("name").is_none());
+
+    let response = patch_json(app, &key, &uri, "application/json", serde_json::json!({})).await;
+    assert_eq!(response.status(), axum::http::StatusCode::OK);
+    let unchanged: User = json_body(response).await;
+    assert_eq!(unchanged.name, "Alicia");
+    assert_eq!(unchanged.email, "alicia@example.com");
+}
+
+#[tokio::test]
+async fn test_patch_joke_requires_owner() {
+    let (app, mut db, key) = setup_with_db().await;
+
+    let author = create_user(app.clone(), &key, "Author", "author@example.com").await;
+    let other = create_user(app.clone(), &key, "Other", "other@example.com").await;
+    let joke = create_joke(app.clone(), &key, author.id, "Original").await;
+    let uri = format!("/joke/{}", joke.id);
+    let body = serde_json::json!({ "content": "Patched" });
+
+    let other_key = user_key(&mut db, other.id).await;
+    let response = patch_json(
+        app.clone(),
+        &other_key,
+        &uri,
+        "application/json",
+        body.clone(),
+    )
+    .await;
+    assert_eq!(response.status(), axum::http::StatusCode::FORBIDDEN);
+
+    let author_key = user_key(&mut db, author.id).await;
+    let response = patch_json(app.clone(), &author_key, &uri, "application/json", body).await;
+    assert_eq!(response.status(), axum::http::StatusCode::OK);
+    let patched: Joke = json_body(response).await;
+    assert_eq!(patched.content, "Patched");
+
+    let response = patch_json(
+        app,
+        &key,
+        "/joke/99999",
+        "application/json",
+        serde_json::json!({ "content": "Nope" }),
+    )
+    .await;
+    assert_eq!(response.status(), axum::http::StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_conditional_get_returns_not_modified() {
+    let (app, key) = setup().await;
+
+    let user = create_user(app.clone(), &key, "Alice", "alice@example.com").await;
+    let joke = create_joke(app.clone(), &key, user.id, "Cached joke").await;
+
+    for uri in [format!("/joke/{}", joke.id), format!("/user/{}", user.id)] {
+        let get_req = Request::builder().uri(&uri).body(Body::empty()).unwrap();
+        let response = app.clone().oneshot(get_req).await.unwrap();
+        assert_eq!(response.status(), axum::http::StatusCode::OK);
+        let etag = response.headers().get("etag").unwrap().clone();
+        assert!(etag.to_str().unwrap().starts_with('"'));
+
+        let get_req = Request::builder()
+            .uri(&uri)
+            .header("if-none-match", etag.clone())
+            .body(Body::empty())
+            .unwrap();
+        let response = app.clone().oneshot(get_req).await.unwrap();
+        assert_eq!(response.status(), axum::http::StatusCode::NOT_MODIFIED);
+        assert_eq!(response.headers().get("etag").unwrap(), &etag);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        assert!(body.is_empty());
+
+        let get_req = Request::builder()
+            .uri(&uri)
+            .header("if-none-match", "\"stale\"")
+            .body(Body::empty())
+            .unwrap();
+        let response = app.clone().oneshot(get_req).await.unwrap();
+        assert_eq!(response.status(), axum::http::StatusCode::OK);
+    }
+}
+
+#[tokio::test]
+async fn test_if_match_prevents_lost_updates() {
+    let (app, key) = setup().await;
+
+    let user = create_user(app.clone(), &key, "Alice", "alice@example.com").await;
+    let joke = create_joke(app.clone(), &key, user.id, "First draft").await;
+    let uri = format!("/joke/{}", joke.id);
+
+    let get_req = Request::builder().uri(&uri).body(Body::empty()).unwrap();
+    let response = app.clone().oneshot(get_req).await.unwrap();
+    let original = response.headers().get("etag").unwrap().clone();
+
+    let put = |etag: axum::http::HeaderValue, content: &str| {
+        Request::builder()
+            .method("PUT")
+            .uri(&uri)
+            .header("authorization", format!("Bearer {key}"))
+            .header("content-type", "application/json")
+            .header("if-match", etag)
+            .body(Body::from(
+                serde_json::to_string(&JokeRequest {
+                    content: content.to_string(),
+                    tags: None,
+                })
+                .unwrap(),
+            ))
+            .unwrap()
+    };
+
+    // The first editor wins and gets the new version back.
+    let response = app
+        .clone()
+        .oneshot(put(original.clone(), "Second draft"))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), axum::http::StatusCode::OK);
+    let updated = response.headers().get("etag").unwrap().clone();
+    assert_ne!(updated, original);
+
+    // The second editor still holds the old version.
+    let response = app
+        .clone()
+        .oneshot(put(original.clone(), "Conflicting draft"))
+        .await
+        .unwrap();
+    assert_eq!(
+        response.status(),
+        axum::http::StatusCode::PRECONDITION_FAILED
+    );
+    assert_eq!(
+        response.headers().get("content-type").unwrap(),
+        "application/problem+json"
+    );
+
+    let delete_req = Request::builder()
+        .method("DELETE")
+        .uri(&uri)
+        .header("authorization", format!("Bearer {key}"))
+        .header("if-match", original)
+        .body(Body::empty())
+        .unwrap();
+    let response = app.clone().oneshot(delete_req).await.unwrap();
+    assert_eq!(
+        response.status(),
+        axum::http::StatusCode::PRECONDITION_FAILED
+    );
+
+    let get_req = Request::builder().uri(&uri).body(Body::empty()).unwrap();
+    let response = app.clone().oneshot(get_req).await.unwrap();
+    let current: Joke = json_body(response).await;
+    assert_eq!(current.content, "Second draft");
+
+    let delete_req = Request::builder()
+        .method("DELETE")
+        .uri(&uri)
+        .header("authorization", format!("Bearer {key}"))
+        .header("if-match", updated)
+        .body(Body::empty())
+        .unwrap();
+    let response = app.oneshot(delete_req).await.unwrap();
+    assert_eq!(response.status(), axum::http::StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_paginate_users_and_user_jokes() {
+    let (app, mut db, key) = setup_with_db().await;
+
+    for i in 0..3 {
+        create_user(
+            app.clone(),
+            &key,
+            &format!("User {i}"),
+            &format!("u{i}@example.com"),
+        )
+        .await;
+    }
+
+    let get_req = Request::builder()
+        .uri("/users?page_size=2")
+        .body(Body::empty())
+        .unwrap();
+    let response = app.clone().oneshot(get_req).await.unwrap();
+    let page: SerializablePage<User> = json_body(response).await;
+    assert_eq!(page.items.len(), 2);
+    let cursor = page.cursor.expect("expected a cursor for the next page");
+
+    let get_req = Request::builder()
+        .uri(format!("/users?page_size=2&cursor={cursor}"))
+        .body(Body::empty())
+        .unwrap();
+    let response = app.clone().oneshot(get_req).await.unwrap();
+    let page: SerializablePage<User> = json_body(response).await;
+    assert_eq!(page.items.len(), 1);
+    assert_eq!(page.items[0].name, "User 2");
+    assert!(page.cursor.is_none());
+
+    // Page sizes are capped by the server.
+    let user = User::get_by_id(&mut db, page.items[0].id).await.unwrap();
+    for i in 0..105 {
+        toasty::create!(in user.jokes() { content: format!("Joke {i}") })
+            .exec(&mut db)
+            .await
+            .unwrap();
+    }
+
+    let get_req = Request::builder()
+        .uri(format!("/users/{}/jokes?page_size=1000", user.id))
+        .body(Body::empty())
+        .unwrap();
+    let response = app.clone().oneshot(get_req).await.unwrap();
+    assert_eq!(response.status(), axum::http::StatusCode::OK);
+    let page: SerializablePage<Joke> = json_body(response).await;
+    assert_eq!(page.items.len(), 100);
+    let cursor = page.cursor.expect("expected a cursor for the next page");
+
+    let get_req = Request::builder()
+        .uri(format!("/users/{}/jokes?cursor={cursor}", user.id))
+        .body(Body::empty())
+        .unwrap();
+    let response = app.oneshot(get_req).await.unwrap();
+    let page: SerializablePage<Joke> = json_body(response).await;
+    assert_eq!(page.items.len(), 5);
+    assert_eq!(page.items[0].content, "Joke 100");
+}
+
+#[tokio::test]
+async fn test_paginate_backwards_newest_first() {
+    let (app, key) = setup().await;
+
+    let user = create_user(app.clone(), &key, "Rita", "rita@example.com").await;
+    let mut ids = Vec::new();
+    for i in 0..5 {
+        ids.push(
+            create_joke(app.clone(), &key, user.id, &format!("Joke {i}"))
+                .await
+                .id,
+        );
+    }
+    ids.reverse();
+
+    let fetch = |query: String| {
+        let app = app.clone();
+        async move {
+            let get_req = Request::builder()
+                .uri(format!("/jokes/paginate?order=desc&page_size=2{query}"))
+                .body(Body::empty())
+                .unwrap();
+            let response = app.oneshot(get_req).await.unwrap();
+            assert_eq!(response.status(), axum::http::StatusCode::OK);
+            let page: SerializablePage<Joke> = json_body(response).await;
+            page
+        }
+    };
+    let page_ids =
+        |page: &SerializablePage<Joke>| page.items.iter().map(|j| j.id).collect::<Vec<_>>();
+
+    let first = fetch(String::new()).await;
+    assert_eq!(page_ids(&first), ids[0..2]);
+    assert!(
+        first.prev_cursor.is_none(),
+        "nothing precedes the first page"
+    );
+
+    let second = fetch(format!("&cursor={}", first.cursor.as_ref().unwrap())).await;
+    assert_eq!(page_ids(&second), ids[2..4]);
+
+    let prev_cursor = second.prev_cursor.unwrap();
+    let back = fetch(format!("&before={prev_cursor}")).await;
+    assert_eq!(page_ids(&back), ids[0..2]);
+    assert_eq!(back.cursor, first.cursor);
+
+    let get_req = Request::builder()
+        .uri(format!(
+            "/jokes/paginate?cursor={}&before={prev_cursor}",
+            first.cursor.unwrap()
+        ))
+        .body(Body::empty())
+        .unwrap();
+    let response = app.oneshot(get_req).await.unwrap();
+    assert_eq!(response.status(), axum::http::StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_pagination_cursors_are_opaque_and_signed() {
+    let (app, key) = setup().await;
+
+    let user = create_user(app.clone(), &key, "Sam", "sam@example.com").await;
+    for i in 0..3 {
+        create_joke(app.clone(), &key, user.id, &format!("Joke {i}")).await;
+    }
+
+    let get_req = Request::builder()
+        .uri("/jokes/paginate?page_size=1")
+        .body(Body::empty())
+        .unwrap();
+    let response = app.clone().oneshot(get_req).await.unwrap();
+    let page: SerializablePage<Joke> = json_body(response).await;
+    let cursor = page.cursor.unwrap();
+    assert!(cursor.parse::<i64>().is_err(), "cursor should be opaque");
+
+    let (payload, tag) = cursor.split_once('.').unwrap();
+    let mut forged = payload.to_string();
+    forged.push('A');
+    let invalid = [
+        "1".to_string(),
+        "not-a-cursor".to_string(),
+        format!("{forged}.{tag}"),
+        format!("{payload}.{}", &tag[1..]),
+    ];
+    for bad in invalid {
+        for param in ["cursor", "before"] {
+            let get_req = Request::builder()
+                .uri(format!("/jokes/paginate?{param}={bad}"))
+                .body(Body::empty())
+                .unwrap();
+            let response = app.clone().oneshot(get_req).await.unwrap();
+            assert_eq!(response.status(), axum::http::StatusCode::BAD_REQUEST);
+            let problem: serde_json::Value = json_body(response).await;
+            assert_eq!(problem["detail"], "Invalid pagination cursor");
+        }
+    }
+
+    let get_req = Request::builder()
+        .uri(format!("/jokes/paginate?page_size=1&cursor={cursor}"))
+        .body(Body::empty())
+        .unwrap();
+    let response = app.oneshot(get_req).await.unwrap();
+    assert_eq!(response.status(), axum::http::StatusCode::OK);
+    let page: SerializablePage<Joke> = json_body(response).await;
+    assert_eq!(page.items[0].content, "Joke 1");
+}
+
+#[tokio::test]
+async fn test_paginated_link_headers_and_total() {
+    let (app, key) = setup().await;
+
+    let user = create_user(app.clone(), &key, "Tess", "tess@example.com").await;
+    for i in 0..5 {
+        create_joke(app.clone(), &key, user.id, &format!("Joke {i}")).await;
+    }
+
+    let get_req = Request::builder()
+        .uri("/jokes/paginate?page_size=2&order=desc")
+        .body(Body::empty())
+        .unwrap();
+    let response = app.clone().oneshot(get_req).await.unwrap();
+    let link = response
+        .headers()
+        .get("link")
+        .unwrap()
+        .to_str()
+        .unwrap()
+        .to_string();
+    let page: SerializablePage<Joke> = json_body(response).await;
+    assert!(page.total.is_none());
+    let cursor = page.cursor.unwrap();
+    assert_eq!(
+        link,
+        format!("</jokes/paginate?page_size=2&order=desc&cursor={cursor}>; rel=\"next\"")
+    );
+
+    // Follow the `next` link as-is.
+    let next = link.trim_start_matches('<').split('>').next().unwrap();
+    let get_req = Request::builder()
+        .uri(format!("{next}&include_total=true"))
+        .body(Body::empty())
+        .unwrap();
+    let response = app.clone().oneshot(get_req).await.unwrap();
+    let link = response
+        .headers()
+        .get("link")
+        .unwrap()
+        .to_str()
+        .unwrap()
+        .to_string();
+    let page: SerializablePage<Joke> = json_body(response).await;
+    assert_eq!(page.items.len(), 2);
+    assert_eq!(page.total, Some(5));
+    assert!(link.contains("rel=\"next\""));
+    assert!(link.contains(&format!(
+        "before={}>; rel=\"prev\"",
+        page.prev_cursor.unwrap()
+    )));
+    assert!(!link.contains(&cursor), "stale cursor must be replaced");
+
+    let get_req = Request::builder()
+        .uri(format!("/users/{}/jokes?include_total=true", user.id))
+        .body(Body::empty())
+        .unwrap();
+    let response = app.oneshot(get_req).await.unwrap();
+    assert!(response.headers().get("link").is_none());
+    let page: SerializablePage<Joke> = json_body(response).await;
+    assert_eq!(page.total, Some(5));
+}
+
+#[tokio::test]
+async fn test_filter_and_sort_jokes() {
+    let (app, key) = setup().await;
+
+    let alice = create_user(app.clone(), &key, "Alice", "alice@example.com").await;
+    let bob = create_user(app.clone(), &key, "Bob", "bob@example.com").await;
+    let first = create_joke(app.clone(), &key, alice.id, "Knock knock").await;
+    let second = create_joke(app.clone(), &key, alice.id, "Why did the chicken").await;
+    let third = create_joke(app.clone(), &key, alice.id, "100% pun_intended").await;
+    let fourth = create_joke(app.clone(), &key, bob.id, "knock it off").await;
+
+    let list = |query: String| {
+        let app = app.clone();
+        async move {
+            let get_req = Request::builder()
+                .uri(format!("/jokes?{query}"))
+                .body(Body::empty())
+                .unwrap();
+            let response = app.oneshot(get_req).await.unwrap();
+            assert_eq!(response.status(), axum::http::StatusCode::OK, "{query}");
+            let jokes: Vec<Joke> = json_body(response).await;
+            jokes.into_iter().map(|j| j.id).collect::<Vec<_>>()
+        }
+    };
+
+    assert_eq!(
+        list(format!("user_id={}", alice.id)).await,
+        [first.id, second.id, third.id]
+    );
+    assert_eq!(list("contains=KNOCK".into()).await, [first.id, fourth.id]);
+    assert_eq!(list("contains=100%25".into()).await, [third.id]);
+    assert_eq!(list("contains=d_the".into()).await, Vec::<i64>::new());
+    assert_eq!(
+        list(format!(
+            "created_after={}&created_before={}",
+            second.created_at, fourth.created_at
+        ))
+        .await,
+        [second.id, third.id]
+    );
+
+    // Touch the oldest joke so it becomes the most recently updated.
+    let update_req = Request::builder()
+        .method("PUT")
+        .uri(format!("/joke/{}", first.id))
+        .header("authorization", format!("Bearer {key}"))
+        .header("content-type", "application/json")
+        .body(Body::from(
+            serde_json::to_string(&JokeRequest {
+                content: "Knock knock, again".to_string(),
+                tags: None,
+            })
+            .unwrap(),
+        ))
+        .unwrap();
+    let response = app.clone().oneshot(update_req).await.unwrap();
+    assert_eq!(response.status(), axum::http::StatusCode::OK);
+
+    let expected = [first.id, fourth.id, third.id, second.id];
+    assert_eq!(list("sort=-updated_at,id".into()).await, expected);
+
+    // Walking the same order one page at a time neither skips nor repeats.
+    let mut seen = Vec::new();
+    let mut cursor = String::new();
+    loop {
+        let get_req = Request::builder()
+            .uri(format!(
+                "/jokes/paginate?sort=-updated_at&page_size=1{cursor}"
+            ))
+            .body(Body::empty())
+            .unwrap();
+        let response = app.clone().oneshot(get_req).await.unwrap();
+        let page: SerializablePage<Joke> = json_body(response).await;
+        seen.extend(page.items.iter().map(|j| j.id));
+        match page.cursor {
+            Some(next) => cursor = format!("&cursor={next}"),
+            None => break,
+        }
+    }
+    assert_eq!(seen, expected);
+
+    for query in ["sort=content", "sort=id,id", "sort=id&order=desc"] {
+        let get_req = Request::builder()
+            .uri(format!("/jokes/paginate?{query}"))
+            .body(Body::empty())
+            .unwrap();
+        let response = app.clone().oneshot(get_req).await.unwrap();
+        assert_eq!(
+            response.status(),
+            axum::http::StatusCode::BAD_REQUEST,
+            "{query}"
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_search_install_indexes_existing_jokes_once() {
+    let (app, mut db, key) = setup_with_db().await;
+    let alice = create_user(app.clone(), &key, "Alice", "alice@example.com").await;
+    let search = |query: &'static str| {
+        let app = app.clone();
+        async move {
+            let get_req = Request::builder()
+                .uri(format!("/jokes/search?q={query}"))
+                .body(Body::empty())
+                .unwrap();
+            let page: SerializablePage<search::SearchHit> =
+                json_body(app.oneshot(get_req).await.unwrap()).await;
+            page.items
+                .into_iter()
+                .map(|hit| hit.joke.id)
+                .collect::<Vec<_>>()
+        }
+    };
+
+    // A database from before search gets its jokes indexed.
+    for sql in [
+        "DROP TRIGGER jokes_fts_insert",
+        "DROP TRIGGER jokes_fts_delete",
+        "DROP TRIGGER jokes_fts_update",
+        "DROP TABLE jokes_fts",
+    ] {
+        toasty::sql::statement(sql).exec(&mut db).await.unwrap();
+    }
+    let cats = create_joke(app.clone(), &key, alice.id, "Cats").await;
+    search::install(&mut db).await.unwrap();
+    assert_eq!(search("cats").await, [cats.id]);
+
+    // Once the index exists, startup leaves it to the triggers rather than
+    // rebuilding it: a joke written while they were missing stays unindexed.
+    toasty::sql::statement("DROP TRIGGER jokes_fts_insert")
+        .exec(&mut db)
+        .await
+        .unwrap();
+    create_joke(app.clone(), &key, alice.id, "Dogs").await;
+    search::install(&mut db).await.unwrap();
+    assert!(search("dogs").await.is_empty());
+    let dogs = create_joke(app.clone(), &key, alice.id, "More dogs").await;
+    assert_eq!(search("dogs").await, [dogs.id]);
+}
+
+#[tokio::test]
+async fn test_search_jokes() {
+    let (app, key) = setup().await;
+    let alice = create_user(app.clone(), &key, "Alice", "alice@example.com").await;
+    let pun = create_joke(app.clone(), &key, alice.id, "A pun about <cats> and dogs").await;
+    let cats = create_joke(app.clone(), &key, alice.id, "Cats, cats, cats: a cat pun").await;
+    let other = create_joke(app.clone(), &key, alice.id, "Knock knock").await;
+
+    let search = |query: &'static str| {
+        let app = app.clone();
+        async move {
+            let get_req = Request::builder()
+                .uri(format!("/jokes/search?{query}"))
+                .body(Body::empty())
+                .unwrap();
+            app.oneshot(get_req).await.unwrap()
+        }
+    };
+    let ids = |page: &SerializablePage<search::SearchHit>| -> Vec<i64> {
+        page.items.iter().map(|hit| hit.joke.id).collect()
+    };
+
+    // The joke mentioning cats most often ranks first, and matches are
+    // highlighted in HTML-escaped snippets.
+    let page: SerializablePage<search::SearchHit> = json_body(search("q=cats").await).await;
+    assert_eq!(ids(&page), [cats.id, pun.id]);
+    assert!(page.items[1].snippet.contains("&lt;<mark>cats</mark>&gt;"));
+    assert!(page.cursor.is_none());
+
+    // Every term must match; FTS syntax in the query is taken literally.
+    let page: SerializablePage<search::SearchHit> = json_body(search("q=pun%20dogs").await).await;
+    assert_eq!(ids(&page), [pun.id]);
+    let page: SerializablePage<search::SearchHit> =
+        json_body(search("q=knock%20OR%20cats").await).await;
+    assert!(page.items.is_empty());
+
+    // Pages walk the ranking forwards and back.
+    let response = search("q=pun&page_size=1&include_total=true").await;
+    let first: SerializablePage<search::SearchHit> = json_body(response).await;
+    assert_eq!(first.total, Some(2));
+    let next = first.cursor.clone().unwrap();
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri(format!("/jokes/search?q=pun&page_size=1&cursor={next}"))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let second: SerializablePage<search::SearchHit> = json_body(response).await;
+    assert_eq!(second.items.len(), 1);
+    assert_ne!(second.items[0].joke.id, first.items[0].joke.id);
+    assert!(second.cursor.is_none());
+    let prev = second.prev_cursor.unwrap();
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri(format!("/jokes/search?q=pun&page_size=1&before={prev}"))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let back: SerializablePage<search::SearchHit> = json_body(response).await;
+    assert_eq!(ids(&back), ids(&first));
+    assert!(back.prev_cursor.is_none());
+
+    // Edits and deletes are reflected in the index.
+    let update_req = Request::builder()
+        .method("PUT")
+        .uri(format!("/joke/{}", other.id))
+        .header("authorization", format!("Bearer {key}"))
+        .header("content-type", "application/json")
+        .body(Body::from(
+            serde_json::to_string(&JokeRequest {
+                content: "Knock knock. Who's there? Cats.".to_string(),
+                tags: None,
+            })
+            .unwrap(),
+        ))
+        .unwrap();
+    let response = app.clone().oneshot(update_req).await.unwrap();
+    assert_eq!(response.status(), axum::http::StatusCode::OK);
+    let delete_req = Request::builder()
+        .method("DELETE")
+        .uri(format!("/joke/{}", pun.id))
+        .header("authorization", format!("Bearer {key}"))
+        .body(Body::empty())
+        .unwrap();
+    let response = app.clone().oneshot(delete_req).await.unwrap();
+    assert_eq!(response.status(), axum::http::StatusCode::OK);
+    let page: SerializablePage<search::SearchHit> = json_body(search("q=cats").await).await;
+    assert_eq!(ids(&page), [cats.id, other.id]);
+    let page: SerializablePage<search::SearchHit> = json_body(search("q=dogs").await).await;
+    assert!(page.items.is_empty());
+
+    for query in ["q=", "q=%20%20", "q=cats&sort=id", "q=cats&cursor=bogus"] {
+        let response = search(query).await;
+        assert_eq!(
+            response.status(),
+            axum::http::StatusCode::BAD_REQUEST,
+            "{query}"
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_random_and_daily_jokes() {
+    let (app, mut db, key) = setup_with_db().await;
+
+    let get = |uri: String| {
+        let app = app.clone();
+        async move {
+            let get_req = Request::builder().uri(uri).body(Body::empty()).unwrap();
+            app.oneshot(get_req).await.unwrap()
+        }
+    };
+
+    for uri in ["/jokes/random", "/jokes/daily"] {
+        let response = get(uri.to_string()).await;
+        assert_eq!(
+            response.status(),
+            axum::http::StatusCode::NOT_FOUND,
+            "{uri}"
+        );
+    }
+
+    let alice = create_user(app.clone(), &key, "Alice", "alice@example.com").await;
+    let bob = create_user(app.clone(), &key, "Bob", "bob@example.com").await;
+    let mut jokes = Vec::new();
+    for content in ["One", "Two", "Three"] {
+        jokes.push(create_joke(app.clone(), &key, alice.id, content).await);
+    }
+    let bobs = create_joke(app.clone(), &key, bob.id, "Bob's only joke").await;
+
+    for _ in 0..5 {
+        let response = get(format!("/jokes/random?user_id={}", bob.id)).await;
+        assert_eq!(response.status(), axum::http::StatusCode::OK);
+        let joke: Joke = json_body(response).await;
+        assert_eq!(joke.id, bobs.id);
+    }
+    let response = get("/jokes/random?user_id=999".to_string()).await;
+    assert_eq!(response.status(), axum::http::StatusCode::NOT_FOUND);
+
+    // Everything but Bob's joke was picked in the last few days, so it is
+    // the only fresh candidate.
+    let today = jiff::Timestamp::now()
+        .to_zoned(jiff::tz::TimeZone::UTC)
+        .date();
+    for (days_ago, joke) in (1..).zip(&jokes) {
+        toasty::create!(DailyJoke {
+            day: today.checked_sub(jiff::Span::new().days(days_ago)).unwrap(),
+            joke_id: joke.id,
+        })
+        .exec(&mut db)
+        .await
+        .unwrap();
+    }
+    let response = get("/jokes/daily".to_string()).await;
+    assert_eq!(response.status(), axum::http::StatusCode::OK);
+    let daily: Joke = json_body(response).await;
+    assert_eq!(daily.id, bobs.id);
+
+    // The pick is stable for the rest of the day, even as jokes are added.
+    create_joke(app.clone(), &key, alice.id, "Four").await;
+    let again: Joke = json_body(get("/jokes/daily".to_string()).await).await;
+    assert_eq!(again.id, daily.id);
+
+    // Deleting today's pick makes way for a new one.
+    let delete_req = Request::builder()
+        .method("DELETE")
+        .uri(format!("/joke/{}", bobs.id))
+        .header("authorization", format!("Bearer {key}"))
+        .body(Body::empty())
+        .unwrap();
+    let response = app.clone().oneshot(delete_req).await.unwrap();
+    assert_eq!(response.status(), axum::http::StatusCode::OK);
+    let replaced: Joke = json_body(get("/jokes/daily".to_string()).await).await;
+    assert_ne!(replaced.id, bobs.id);
+}
+
+async fn put_vote(app: axum::Router, key: &str, joke_id: i64, value: i64) -> Response {
+    let req = Request::builder()
+        .method("PUT")
+        .uri(format!("/joke/{joke_id}/vote"))
+        .header("authorization", format!("Bearer {key}"))
+        .header("content-type", "application/json")
+        .body(Body::from(
+            serde_json::json!({ "value": value }).to_string(),
+        ))
+        .unwrap();
+    app.oneshot(req).await.unwrap()
+}
+
+#[tokio::test]
+async fn test_votes_and_top_sort() {
+    let (app, mut db, key) = setup_with_db().await;
+    let alice = create_user(app.clone(), &key, "Alice", "alice@example.com").await;
+    let bob = create_user(app.clone(), &key, "Bob", "bob@example.com").await;
+    let alice_key = user_key(&mut db, alice.id).await;
+    let bob_key = user_key(&mut db, bob.id).await;
+    let meh = create_joke(app.clone(), &key, alice.id, "Meh").await;
+    let good = create_joke(app.clone(), &key, alice.id, "Good").await;
+    let bad = create_joke(app.clone(), &key, alice.id, "Bad").await;
+
+    let score = |id: i64| {
+        let app = app.clone();
+        async move {
+            let get_req = Request::builder()
+                .uri(format!("/joke/{id}"))
+                .body(Body::empty())
+                .unwrap();
+            let joke: Joke = json_body(app.oneshot(get_req).await.unwrap()).await;
+            joke.score
+        }
+    };
+
+    let response = put_vote(app.clone(), &alice_key, good.id, 1).await;
+    assert_eq!(response.status(), axum::http::StatusCode::OK);
+    let response = put_vote(app.clone(), &bob_key, good.id, 1).await;
+    assert_eq!(response.status(), axum::http::StatusCode::OK);
+    // Voting again replaces the earlier vote rather than adding to it.
+    put_vote(app.clone(), &bob_key, good.id, 1).await;
+    put_vote(app.clone(), &bob_key, bad.id, 1).await;
+    put_vote(app.clone(), &bob_key, bad.id, -1).await;
+    put_vote(app.clone(), &alice_key, bad.id, -1).await;
+    assert_eq!(score(good.id).await, 2);
+    assert_eq!(score(bad.id).await, -2);
+    assert_eq!(score(meh.id).await, 0);
+
+    let get_req = Request::builder()
+        .uri("/jokes?sort=top")
+        .body(Body::empty())
+        .unwrap();
+    let jokes: Vec<Joke> = json_body(app.clone().oneshot(get_req).await.unwrap()).await;
+    let ids: Vec<i64> = jokes.iter().map(|j| j.id).collect();
+    assert_eq!(ids, [good.id, meh.id, bad.id]);
+
+    // Withdrawing a vote takes it out of the score, and only once.
+    let delete = |key: String, id: i64| {
+        let app = app.clone();
+        async move {
+            let req = Request::builder()
+                .method("DELETE")
+                .uri(format!("/joke/{id}/vote"))
+                .header("authorization", format!("Bearer {key}"))
+                .body(Body::empty())
+                .unwrap();
+            app.oneshot(req).await.unwrap().status()
+        }
+    };
+    assert_eq!(
+        delete(alice_key.clone(), bad.id).await,
+        axum::http::StatusCode::OK
+    );
+    assert_eq!(
+        delete(alice_key.clone(), bad.id).await,
+        axum::http::StatusCode::NOT_FOUND
+    );
+    assert_eq!(score(bad.id).await, -1);
+
+    // Votes must be +/-1 and cast by a user, not an operator key.
+    let response = put_vote(app.clone(), &alice_key, meh.id, 2).await;
+    assert_eq!(response.status(), axum::http::StatusCode::BAD_REQUEST);
+    let response = put_vote(app.clone(), &key, meh.id, 1).await;
+    assert_eq!(response.status(), axum::http::StatusCode::FORBIDDEN);
+    let response = put_vote(app.clone(), &alice_key, 999, 1).await;
+    assert_eq!(response.status(), axum::http::StatusCode::NOT_FOUND);
+
+    // Deleting a voter withdraws their votes.
+    let delete_req = Request::builder()
+        .method("DELETE")
+        .uri(format!("/user/{}", bob.id))
+        .header("authorization", format!("Bearer {key}"))
+        .body(Body::empty())
+        .unwrap();
+    let response = app.clone().oneshot(delete_req).await.unwrap();
+    assert_eq!(response.status(), axum::http::StatusCode::OK);
+    assert_eq!(score(good.id).await, 1);
+    assert_eq!(score(bad.id).await, 0);
+}
+
+#[tokio::test]
+async fn test_tags() {
+    let (app, key) = setup().await;
+    let alice = create_user(app.clone(), &key, "Alice", "alice@example.com").await;
+
+    let post_tagged = |content: &'static str, tags: serde_json::Value| {
+        let app = app.clone();
+        let key = key.clone();
+        async move {
+            let req = Request::builder()
+                .method("POST")
+                .uri(format!("/users/{}/jokes", alice.id))
+                .header("authorization", format!("Bearer {key}"))
+                .header("content-type", "application/json")
+                .body(Body::from(
+                    serde_json::json!({ "content": content, "tags": tags }).to_string(),
+                ))
+                .unwrap();
+            app.oneshot(req).await.unwrap()
+        }
+    };
+    let tag_names = |joke: &Joke| -> Vec<String> {
+        let mut names: Vec<String> = joke.tags.get().iter().map(|t| t.name.clone()).collect();
+        names.sort();
+        names
+    };
+
+    let response = post_tagged("Knock knock", serde_json::json!(["knock-knock", "classic"])).await;
+    assert_eq!(response.status(), axum::http::StatusCode::CREATED);
+    let knock: Joke = json_body(response).await;
+    assert_eq!(tag_names(&knock), ["classic", "knock-knock"]);
+    let response = post_tagged("Pun", serde_json::json!(["pun", "classic", "pun"])).await;
+    let pun: Joke = json_body(response).await;
+    assert_eq!(tag_names(&pun), ["classic", "pun"]);
+    create_joke(app.clone(), &key, alice.id, "Untagged").await;
+
+    for tags in [
+        serde_json::json!(["Upper"]),
+        serde_json::json!(["-dash"]),
+        serde_json::json!(["with space"]),
+        serde_json::json!((0..11).map(|i| format!("t{i}")).collect::<Vec<_>>()),
+    ] {
+        let response = post_tagged("Bad tags", tags.clone()).await;
+        assert_eq!(
+            response.status(),
+            axum::http::StatusCode::BAD_REQUEST,
+            "{tags}"
+        );
+    }
+
+    let get_req = Request::builder().uri("/tags").body(Body::empty()).unwrap();
+    let usage: Vec<serde_json::Value> =
+        json_body(app.clone().oneshot(get_req).await.unwrap()).await;
+    assert_eq!(
+        usage,
+        [
+            serde_json::json!({ "name": "classic", "jokes": 2 }),
+            serde_json::json!({ "name": "knock-knock", "jokes": 1 }),
+            serde_json::json!({ "name": "pun", "jokes": 1 }),
+        ]
+    );
+
+    let list = |uri: &'static str| {
+        let app = app.clone();
+        async move {
+            let get_req = Request::builder().uri(uri).body(Body::empty()).unwrap();
+            let response = app.oneshot(get_req).await.unwrap();
+            assert_eq!(response.status(), axum::http::StatusCode::OK);
+            let page: SerializablePage<Joke> = json_body(response).await;
+            page.items.iter().map(|j| j.id).collect::<Vec<_>>()
+        }
+    };
+    assert_eq!(list("/tags/classic/jokes").await, [knock.id, pun.id]);
+    assert_eq!(
+        list("/tags/classic/jokes?order=desc&page_size=1").await,
+        [pun.id]
+    );
+
+    // Patching tags replaces them; leaving them out keeps them.
+    let response = patch_json(
+        app.clone(),
+        &key,
+        &format!("/joke/{}", pun.id),
+        "application/json",
+        serde_json::json!({ "tags": ["wordplay"] }),
+    )
+    .await;
+    let patched: Joke = json_body(response).await;
+    assert_eq!(tag_names(&patched), ["wordplay"]);
+    let response = patch_json(
+        app.clone(),
+        &key,
+        &format!("/joke/{}", pun.id),
+        "application/json",
+        serde_json::json!({ "content": "Better pun" }),
+    )
+    .await;
+    let patched: Joke = json_body(response).await;
+    assert_eq!(tag_names(&patched), ["wordplay"]);
+    assert_eq!(list("/tags/classic/jokes").await, [knock.id]);
+
+    // Under merge-patch, `null` removes them.
+    let response = patch_json(
+        app.clone(),
+        &key,
+        &format!("/joke/{}", pun.id),
+        "application/merge-patch+json",
+        serde_json::json!({ "tags": null }),
+    )
+    .await;
+    let patched: Joke = json_body(response).await;
+    assert_eq!(patched.content, "Better pun");
+    assert!(tag_names(&patched).is_empty());
+    let response = patch_json(
+        app.clone(),
+        &key,
+        &format!("/joke/{}", pun.id),
+        "application/merge-patch+json",
+        serde_json::json!({ "tags": ["Upper"] }),
+    )
+    .await;
+    assert_eq!(response.status(), axum::http::StatusCode::BAD_REQUEST);
+
+    let get_req = Request::builder()
+        .uri("/tags/nope/jokes")
+        .body(Body::empty())
+        .unwrap();
+    let response = app.clone().oneshot(get_req).await.unwrap();
+    assert_eq!(response.status(), axum::http::StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_comment_threads() {
+    let (app, mut db, key) = setup_with_db().await;
+    let alice = create_user(app.clone(), &key, "Alice", "alice@example.com").await;
+    let bob = create_user(app.clone(), &key, "Bob", "bob@example.com").await;
+    let alice_key = user_key(&mut db, alice.id).await;
+    let bob_key = user_key(&mut db, bob.id).await;
+    let joke = create_joke(app.clone(), &key, alice.id, "Knock knock").await;
+    let other = create_joke(app.clone(), &key, alice.id, "Other").await;
+
+    let comment = |key: String, joke_id: i64, body: serde_json::Value| {
+        let app = app.clone();
+        async move {
+            let req = Request::builder()
+                .method("POST")
+                .uri(format!("/joke/{joke_id}/comments"))
+                .header("authorization", format!("Bearer {key}"))
+                .header("content-type", "application/json")
+                .body(Body::from(body.to_string()))
+                .unwrap();
+            app.oneshot(req).await.unwrap()
+        }
+    };
+    let list = |query: String| {
+        let app = app.clone();
+        async move {
+            let get_req = Request::builder()
+                .uri(format!("/joke/{}/comments{query}", joke.id))
+                .body(Body::empty())
+                .unwrap();
+            let response = app.oneshot(get_req).await.unwrap();
+            assert_eq!(response.status(), axum::http::StatusCode::OK);
+            let page: SerializablePage<Comment> = json_body(response).await;
+            page.items.iter().map(|c| c.id).collect::<Vec<_>>()
+        }
+    };
+
+    let response = comment(
+        bob_key.clone(),
+        joke.id,
+        serde_json::json!({ "content": "Who's there?" }),
+    )
+    .await;
+    assert_eq!(response.status(), axum::http::StatusCode::CREATED);
+    let top: Comment = json_body(response).await;
+    assert_eq!(top.user_id, bob.id);
+    let response = comment(
+        alice_key.clone(),
+        joke.id,
+        serde_json::json!({ "content": "Lettuce", "parent_id": top.id }),
+    )
+    .await;
+    let reply: Comment = json_body(response).await;
+    let response = comment(
+        bob_key.clone(),
+        joke.id,
+        serde_json::json!({ "content": "Lettuce who?", "parent_id": reply.id }),
+    )
+    .await;
+    let nested: Comment = json_body(response).await;
+    let response = comment(
+        alice_key.clone(),
+        joke.id,
+        serde_json::json!({ "content": "Second thread" }),
+    )
+    .await;
+    let second: Comment = json_body(response).await;
+
+    assert_eq!(list(String::new()).await, [top.id, second.id]);
+    assert_eq!(list(format!("?parent_id={}", top.id)).await, [reply.id]);
+    assert_eq!(
+        list("?order=desc&page_size=1".to_string()).await,
+        [second.id]
+    );
+
+    // Replies stay on their parent's joke, and operator keys cannot comment.
+    let response = comment(
+        bob_key.clone(),
+        other.id,
+        serde_json::json!({ "content": "Wrong joke", "parent_id": top.id }),
+    )
+    .await;
+    assert_eq!(response.status(), axum::http::StatusCode::BAD_REQUEST);
+    let response = comment(key.clone(), joke.id, serde_json::json!({ "content": "Hi" })).await;
+    assert_eq!(response.status(), axum::http::StatusCode::FORBIDDEN);
+
+    // Only the author (or a moderator) may edit.
+    let edit = |key: String, id: i64| {
+        let app = app.clone();
+        async move {
+            let req = Request::builder()
+                .method("PUT")
+                .uri(format!("/joke/{}/comments/{id}", joke.id))
+                .header("authorization", format!("Bearer {key}"))
+                .header("content-type", "application/json")
+                .body(Body::from(
+                    serde_json::json!({ "content": "Edited" }).to_string(),
+                ))
+                .unwrap();
+            app.oneshot(req).await.unwrap().status()
+        }
+    };
+    assert_eq!(
+        edit(alice_key.clone(), top.id).await,
+        axum::http::StatusCode::FORBIDDEN
+    );
+    assert_eq!(
+        edit(bob_key.clone(), top.id).await,
+        axum::http::StatusCode::OK
+    );
+    let get_req = Request::builder()
+        .uri(format!("/joke/{}/comments/{}", other.id, top.id))
+        .body(Body::empty())
+        .unwrap();
+    let response = app.clone().oneshot(get_req).await.unwrap();
+    assert_eq!(response.status(), axum::http::StatusCode::NOT_FOUND);
+
+    // Purging Bob takes his threads with him, including Alice's reply.
+    let delete_req = Request::builder()
+        .method("DELETE")
+        .uri(format!("/user/{}", bob.id))
+        .header("authorization", format!("Bearer {key}"))
+        .body(Body::empty())
+        .unwrap();
+    let response = app.clone().oneshot(delete_req).await.unwrap();
+    assert_eq!(response.status(), axum::http::StatusCode::OK);
+    let later = jiff::Timestamp::now() + jiff::SignedDuration::from_secs(1);
+    purge::purge(&mut db, later).await.unwrap();
+    assert_eq!(list(String::new()).await, [second.id]);
+    for id in [reply.id, nested.id] {
+        let get_req = Request::builder()
+            .uri(format!("/joke/{}/comments/{id}", joke.id))
+            .body(Body::empty())
+            .unwrap();
+        let response = app.clone().oneshot(get_req).await.unwrap();
+        assert_eq!(response.status(), axum::http::StatusCode::NOT_FOUND);
+    }
+
+    // Purging the joke deletes the rest.
+    let delete_req = Request::builder()
+        .method("DELETE")
+        .uri(format!("/joke/{}", joke.id))
+        .header("authorization", format!("Bearer {key}"))
+        .body(Body::empty())
+        .unwrap();
+    let response = app.clone().oneshot(delete_req).await.unwrap();
+    assert_eq!(response.status(), axum::http::StatusCode::OK);
+    let later = jiff::Timestamp::now() + jiff::SignedDuration::from_secs(1);
+    purge::purge(&mut db, later).await.unwrap();
+    assert!(Comment::all().exec(&mut db).await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_favorites() {
+    let (app, mut db, key) = setup_with_db().await;
+    let alice = create_user(app.clone(), &key, "Alice", "alice@example.com").await;
+    let bob = create_user(app.clone(), &key, "Bob", "bob@example.com").await;
+    let alice_key = user_key(&mut db, alice.id).await;
+    let bob_key = user_key(&mut db, bob.id).await;
+    let first = create_joke(app.clone(), &key, alice.id, "First").await;
+    let second = create_joke(app.clone(), &key, alice.id, "Second").await;
+    create_joke(app.clone(), &key, alice.id, "Third").await;
+
+    let favorite = |method: &'static str, key: String, id: i64| {
+        let app = app.clone();
+        async move {
+            let req = Request::builder()
+                .method(method)
+                .uri(format!("/joke/{id}/favorite"))
+                .header("authorization", format!("Bearer {key}"))
+                .body(Body::empty())
+                .unwrap();
+            app.oneshot(req).await.unwrap().status()
+        }
+    };
+    let favorite_count = |id: i64| {
+        let app = app.clone();
+        async move {
+            let get_req = Request::builder()
+                .uri(format!("/joke/{id}"))
+                .body(Body::empty())
+                .unwrap();
+            let joke: Joke = json_body(app.oneshot(get_req).await.unwrap()).await;
+            joke.favorite_count
+        }
+    };
+    let favorites = |user_id: i64, query: &'static str| {
+        let app = app.clone();
+        async move {
+            let get_req = Request::builder()
+                .uri(format!("/users/{user_id}/favorites{query}"))
+                .body(Body::empty())
+                .unwrap();
+            let response = app.oneshot(get_req).await.unwrap();
+            assert_eq!(response.status(), axum::http::StatusCode::OK);
+            let page: SerializablePage<Joke> = json_body(response).await;
+            page.items.iter().map(|j| j.id).collect::<Vec<_>>()
+        }
+    };
+
+    for (key, id) in [
+        (&alice_key, second.id),
+        (&alice_key, first.id),
+        (&alice_key, first.id),
+        (&bob_key, first.id),
+    ] {
+        assert_eq!(
+            favorite("PUT", key.clone(), id).await,
+            axum::http::StatusCode::OK
+        );
+    }
+    assert_eq!(favorite_count(first.id).await, 2);
+    assert_eq!(favorite_count(second.id).await, 1);
+    assert_eq!(favorites(alice.id, "").await, [first.id, second.id]);
+    assert_eq!(favorites(alice.id, "?page_size=1").await, [first.id]);
+    assert_eq!(favorites(bob.id, "").await, [first.id]);
+
+    assert_eq!(
+        favorite("DELETE", alice_key.clone(), second.id).await,
+        axum::http::StatusCode::OK
+    );
+    assert_eq!(
+        favorite("DELETE", alice_key.clone(), second.id).await,
+        axum::http::StatusCode::NOT_FOUND
+    );
+    assert_eq!(favorite_count(second.id).await, 0);
+    assert_eq!(
+        favorite("PUT", key.clone(), first.id).await,
+        axum::http::StatusCode::FORBIDDEN
+    );
+
+    // A deleted user's favorites stop counting.
+    let delete_req = Request::builder()
+        .method("DELETE")
+        .uri(format!("/user/{}", bob.id))
+        .header("authorization", format!("Bearer {key}"))
+        .body(Body::empty())
+        .unwrap();
+    let response = app.clone().oneshot(delete_req).await.unwrap();
+    assert_eq!(response.status(), axum::http::StatusCode::OK);
+    assert_eq!(favorite_count(first.id).await, 1);
+
+    let get_req = Request::builder()
+        .uri("/users/999/favorites")
+        .body(Body::empty())
+        .unwrap();
+    let response = app.clone().oneshot(get_req).await.unwrap();
+    assert_eq!(response.status(), axum::http::StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_reactions() {
+    let (app, mut db, key) = setup_with_db().await;
+    let alice = create_user(app.clone(), &key, "Alice", "alice@example.com").await;
+    let bob = create_user(app.clone(), &key, "Bob", "bob@example.com").await;
+    let alice_key = user_key(&mut db, alice.id).await;
+    let bob_key = user_key(&mut db, bob.id).await;
+    let joke = create_joke(app.clone(), &key, alice.id, "Knock knock").await;
+    assert!(joke.reaction_counts.is_empty());
+
+    let react = |method: &'static str, key: String, kind: &'static str| {
+        let app = app.clone();
+        async move {
+            let req = Request::builder()
+                .method(method)
+                .uri(format!("/joke/{}/reactions/{kind}", joke.id))
+                .header("authorization", format!("Bearer {key}"))
+                .body(Body::empty())
+                .unwrap();
+            app.oneshot(req).await.unwrap().status()
+        }
+    };
+    let reactions = || {
+        let app = app.clone();
+        async move {
+            let get_req = Request::builder()
+                .uri(format!("/joke/{}", joke.id))
+                .body(Body::empty())
+                .unwrap();
+            let body: serde_json::Value = json_body(app.oneshot(get_req).await.unwrap()).await;
+            body["reactions"].clone()
+        }
+    };
+
+    for (key, kind) in [
+        (&alice_key, "joy"),
+        (&alice_key, "thinking"),
+        (&bob_key, "joy"),
+        (&bob_key, "joy"),
+    ] {
+        assert_eq!(
+            react("PUT", key.clone(), kind).await,
+            axum::http::StatusCode::OK
+        );
+    }
+    assert_eq!(
+        reactions().await,
+        serde_json::json!({ "joy": 2, "thinking": 1 })
+    );
+
+    // Counts are in listings too.
+    let get_req = Request::builder()
+        .uri("/jokes")
+        .body(Body::empty())
+        .unwrap();
+    let jokes: Vec<Joke> = json_body(app.clone().oneshot(get_req).await.unwrap()).await;
+    assert_eq!(jokes[0].reaction_counts.get("joy"), Some(&2));
+
+    assert_eq!(
+        react("DELETE", alice_key.clone(), "thinking").await,
+        axum::http::StatusCode::OK
+    );
+    assert_eq!(
+        react("DELETE", alice_key.clone(), "thinking").await,
+        axum::http::StatusCode::NOT_FOUND
+    );
+    assert_eq!(reactions().await, serde_json::json!({ "joy": 2 }));
+    assert_eq!(
+        react("PUT", alice_key.clone(), "thumbs_up").await,
+        axum::http::StatusCode::BAD_REQUEST
+    );
+    assert_eq!(
+        react("PUT", key.clone(), "joy").await,
+        axum::http::StatusCode::FORBIDDEN
+    );
+
+    let delete_req = Request::builder()
+        .method("DELETE")
+        .uri(format!("/user/{}", bob.id))
+        .header("authorization", format!("Bearer {key}"))
+        .body(Body::empty())
+        .unwrap();
+    let response = app.clone().oneshot(delete_req).await.unwrap();
+    assert_eq!(response.status(), axum::http::StatusCode::OK);
+    assert_eq!(reactions().await, serde_json::json!({ "joy": 1 }));
+}
+
+#[tokio::test]
+async fn test_reports_and_hiding() {
+    let (app, mut db, key) = setup_with_db().await;
+    let alice = create_user(app.clone(), &key, "Alice", "alice@example.com").await;
+    let bob = create_user(app.clone(), &key, "Bob", "bob@example.com").await;
+    let alice_key = user_key(&mut db, alice.id).await;
+    let bob_key = user_key(&mut db, bob.id).await;
+    let kept = create_joke(app.clone(), &key, alice.id, "Harmless").await;
+    let reported = create_joke(app.clone(), &key, alice.id, "Offensive").await;
+
+    let send = |method: &'static str, uri: String, key: String, body: serde_json::Value| {
+        let app = app.clone();
+        async move {
+            let req = Request::builder()
+                .method(method)
+                .uri(uri)
+                .header("authorization", format!("Bearer {key}"))
+                .header("content-type", "application/json")
+                .body(Body::from(body.to_string()))
+                .unwrap();
+            app.oneshot(req).await.unwrap()
+        }
+    };
+    let listed = |uri: String| {
+        let app = app.clone();
+        async move {
+            let get_req = Request::builder().uri(uri).body(Body::empty()).unwrap();
+            let response = app.oneshot(get_req).await.unwrap();
+            assert_eq!(response.status(), axum::http::StatusCode::OK);
+            let body: serde_json::Value = json_body(response).await;
+            let jokes = body.get("items").unwrap_or(&body).as_array().unwrap();
+            jokes
+                .iter()
+                .map(|joke| joke["id"].as_i64().unwrap())
+                .collect::<Vec<_>>()
+        }
+    };
+
+    let report_uri = format!("/joke/{}/report", reported.id);
+    for (reporter, reason) in [(&alice_key, "offensive"), (&bob_key, "spam")] {
+        let response = send(
+            "POST",
+            report_uri.clone(),
+            reporter.clone(),
+            serde_json::json!({ "reason": reason, "note": "Not funny" }),
+        )
+        .await;
+        assert_eq!(response.status(), axum::http::StatusCode::CREATED);
+    }
+    let response = send(
+        "POST",
+        report_uri.clone(),
+        bob_key.clone(),
+        serde_json::json!({ "reason": "boring" }),
+    )
+    .await;
+    assert_eq!(response.status(), axum::http::StatusCode::BAD_REQUEST);
+
+    let response = send(
+        "GET",
+        "/reports".to_string(),
+        bob_key.clone(),
+        serde_json::json!({}),
+    )
+    .await;
+    assert_eq!(response.status(), axum::http::StatusCode::FORBIDDEN);
+    let response = send(
+        "GET",
+        "/reports".to_string(),
+        key.clone(),
+        serde_json::json!({}),
+    )
+    .await;
+    assert_eq!(response.status(), axum::http::StatusCode::OK);
+    let queue: serde_json::Value = json_body(response).await;
+    let open = queue["items"].as_array().unwrap();
+    assert_eq!(open.len(), 2);
+    assert_eq!(open[0]["reason"], "offensive");
+    assert_eq!(open[0]["status"], "open");
+    let report_id = open[0]["id"].as_i64().unwrap();
+
+    let resolve_uri = format!("/reports/{report_id}/resolve");
+    let response = send(
+        "POST",
+        resolve_uri.clone(),
+        key.clone(),
+        serde_json::json!({ "status": "open" }),
+    )
+    .await;
+    assert_eq!(response.status(), axum::http::StatusCode::BAD_REQUEST);
+    let response = send(
+        "POST",
+        resolve_uri.clone(),
+        key.clone(),
+        serde_json::json!({ "status": "resolved", "hide_joke": true }),
+    )
+    .await;
+    assert_eq!(response.status(), axum::http::StatusCode::OK);
+    let resolved: serde_json::Value = json_body(response).await;
+    assert_eq!(resolved["status"], "resolved");
+    assert!(resolved["resolved_at"].is_string());
+    let response = send(
+        "POST",
+        resolve_uri,
+        key.clone(),
+        serde_json::json!({ "status": "dismissed" }),
+    )
+    .await;
+    assert_eq!(response.status(), axum::http::StatusCode::BAD_REQUEST);
+
+    // Hiding closed the other report too.
+    let response = send(
+        "GET",
+        "/reports".to_string(),
+        key.clone(),
+        serde_json::json!({}),
+    )
+    .await;
+    let queue: serde_json::Value = json_body(response).await;
+    assert_eq!(queue["items"], serde_json::json!([]));
+    let response = send(
+        "GET",
+        "/reports?status=resolved".to_string(),
+        key.clone(),
+        serde_json::json!({}),
+    )
+    .await;
+    let queue: serde_json::Value = json_body(response).await;
+    assert_eq!(queue["items"].as_array().unwrap().len(), 2);
+
+    assert_eq!(listed("/jokes".to_string()).await, [kept.id]);
+    assert_eq!(listed("/jokes/paginate".to_string()).await, [kept.id]);
+    assert_eq!(
+        listed(format!("/users/{}/jokes", alice.id)).await,
+        [kept.id]
+    );
+    let get_req = Request::builder()
+        .uri(format!("/joke/{}", reported.id))
+        .body(Body::empty())
+        .unwrap();
+    let response = app.clone().oneshot(get_req).await.unwrap();
+    assert_eq!(response.status(), axum::http::StatusCode::OK);
+
+    let hidden_uri = format!("/joke/{}/hidden", reported.id);
+    let response = send("DELETE", hidden_uri.clone(), bob_key, serde_json::json!({})).await;
+    assert_eq!(response.status(), axum::http::StatusCode::FORBIDDEN);
+    let response = send("DELETE", hidden_uri, key, serde_json::json!({})).await;
+    assert_eq!(response.status(), axum::http::StatusCode::OK);
+    assert_eq!(listed("/jokes".to_string()).await, [kept.id, reported.id]);
+}
+
+#[tokio::test]
+async fn test_hiding_a_deleted_joke_leaves_its_report_open() {
+    let (app, mut db, key) = setup_with_db().await;
+    let alice = create_user(app.clone(), &key, "Alice", "alice@example.com").await;
+    let bob = create_user(app.clone(), &key, "Bob", "bob@example.com").await;
+    let alice_key = user_key(&mut db, alice.id).await;
+    let bob_key = user_key(&mut db, bob.id).await;
+    let joke = create_joke(app.clone(), &key, alice.id, "Offensive").await;
+
+    let send = |method: &'static str, uri: String, key: String, body: serde_json::Value| {
+        let app = app.clone();
+        async move {
+            let req = Request::builder()
+                .method(method)
+                .uri(uri)
+                .header("authorization", format!("Bearer {key}"))
+                .header("content-type", "application/json")
+                .body(Body::from(body.to_string()))
+                .unwrap();
+            app.oneshot(req).await.unwrap()
+        }
+    };
+
+    let response = send(
+        "POST",
+        format!("/joke/{}/report", joke.id),
+        bob_key,
+        serde_json::json!({ "reason": "offensive" }),
+    )
+    .await;
+    assert_eq!(response.status(), axum::http::StatusCode::CREATED);
+    let report: serde_json::Value = json_body(response).await;
+    let resolve_uri = format!("/reports/{}/resolve", report["id"]);
+    let response = send(
+        "DELETE",
+        format!("/joke/{}", joke.id),
+        alice_key,
+        serde_json::json!({}),
+    )
+    .await;
+    assert_eq!(response.status(), axum::http::StatusCode::OK);
+
+    let response = send(
+        "POST",
+        resolve_uri.clone(),
+        key.clone(),
+        serde_json::json!({ "status": "resolved", "hide_joke": true }),
+    )
+    .await;
+    assert_eq!(response.status(), axum::http::StatusCode::NOT_FOUND);
+    let response = send(
+        "GET",
+        "/reports".to_string(),
+        key.clone(),
+        serde_json::json!({}),
+    )
+    .await;
+    let queue: serde_json::Value = json_body(response).await;
+    assert_eq!(queue["items"][0]["status"], "open");
+
+    let response = send(
+        "POST",
+        resolve_uri,
+        key,
+        serde_json::json!({ "status": "dismissed" }),
+    )
+    .await;
+    assert_eq!(response.status(), axum::http::StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_soft_delete_restore_and_purge() {
+    let (app, mut db, key) = setup_with_db().await;
+    let alice = create_user(app.clone(), &key, "Alice", "alice@example.com").await;
+    let bob = create_user(app.clone(), &key, "Bob", "bob@example.com").await;
+    let alice_key = user_key(&mut db, alice.id).await;
+    let bob_key = user_key(&mut db, bob.id).await;
+    let first = create_joke(app.clone(), &key, alice.id, "First").await;
+    let second = create_joke(app.clone(), &key, alice.id, "Second").await;
+    let bobs = create_joke(app.clone(), &key, bob.id, "Bob's").await;
+
+    let send = |method: &'static str, uri: String, key: String| {
+        let app = app.clone();
+        async move {
+            let req = Request::builder()
+                .method(method)
+                .uri(uri)
+                .header("authorization", format!("Bearer {key}"))
+                .body(Body::empty())
+                .unwrap();
+            app.oneshot(req).await.unwrap()
+        }
+    };
+    let joke_ids = || {
+        let app = app.clone();
+        async move {
+            let get_req = Request::builder()
+                .uri("/jokes")
+                .body(Body::empty())
+                .unwrap();
+            let jokes: Vec<Joke> = json_body(app.oneshot(get_req).await.unwrap()).await;
+            jokes.iter().map(|j| j.id).collect::<Vec<_>>()
+        }
+    };
+
+    // A deleted joke is gone from reads until its author restores it.
+    let response = send("DELETE", format!("/joke/{}", first.id), alice_key.clone()).await;
+    assert_eq!(response.status(), axum::http::StatusCode::OK);
+    assert_eq!(joke_ids().await, [second.id, bobs.id]);
+    let response = send("GET", format!("/joke/{}", first.id), key.clone()).await;
+    assert_eq!(response.status(), axum::http::StatusCode::NOT_FOUND);
+    let restore_first = format!("/joke/{}/restore", first.id);
+    let response = send("POST", restore_first.clone(), bob_key.clone()).await;
+    assert_eq!(response.status(), axum::http::StatusCode::FORBIDDEN);
+    let response = send("POST", restore_first.clone(), alice_key.clone()).await;
+    assert_eq!(response.status(), axum::http::StatusCode::OK);
+    let restored: Joke = json_body(response).await;
+    assert_eq!(restored.deleted_at, None);
+    assert_eq!(joke_ids().await, [first.id, second.id, bobs.id]);
+
+    // Deleting a user takes their jokes and votes out of view.
+    let response = send("DELETE", format!("/joke/{}", second.id), alice_key.clone()).await;
+    assert_eq!(response.status(), axum::http::StatusCode::OK);
+    let vote_req = Request::builder()
+        .method("PUT")
+        .uri(format!("/joke/{}/vote", bobs.id))
+        .header("authorization", format!("Bearer {alice_key}"))
+        .header("content-type", "application/json")
+        .body(Body::from(r#"{"value":1}"#))
+        .unwrap();
+    let response = app.clone().oneshot(vote_req).await.unwrap();
+    assert_eq!(response.status(), axum::http::StatusCode::OK);
+    let response = send("DELETE", format!("/user/{}", alice.id), key.clone()).await;
+    assert_eq!(response.status(), axum::http::StatusCode::OK);
+    assert_eq!(joke_ids().await, [bobs.id]);
+    let response = send("GET", format!("/joke/{}", bobs.id), key.clone()).await;
+    let joke: Joke = json_body(response).await;
+    assert_eq!(joke.score, 0);
+    let response = send("GET", format!("/user/{}", alice.id), key.clone()).await;
+    assert_eq!(response.status(), axum::http::StatusCode::NOT_FOUND);
+    let response = send("GET", "/users".to_string(), key.clone()).await;
+    let users: SerializablePage<User> = json_body(response).await;
+    assert_eq!(users.items.len(), 1);
+    let response = send("GET", "/users".to_string(), alice_key.clone()).await;
+    assert_eq!(response.status(), axum::http::StatusCode::UNAUTHORIZED);
+    let response = send("POST", restore_first.clone(), key.clone()).await;
+    assert_eq!(response.status(), axum::http::StatusCode::BAD_REQUEST);
+
+    // Restoring the user brings back what went with them, but not the joke
+    // deleted on its own.
+    let restore_alice = format!("/user/{}/restore", alice.id);
+    let response = send("POST", restore_alice.clone(), bob_key.clone()).await;
+    assert_eq!(response.status(), axum::http::StatusCode::FORBIDDEN);
+    let response = send("POST", restore_alice.clone(), key.clone()).await;
+    assert_eq!(response.status(), axum::http::StatusCode::OK);
+    assert_eq!(joke_ids().await, [first.id, bobs.id]);
+    let response = send("GET", format!("/joke/{}", bobs.id), key.clone()).await;
+    let joke: Joke = json_body(response).await;
+    assert_eq!(joke.score, 1);
+
+    // Purging spares rows still inside the retention period.
+    let week_ago = jiff::Timestamp::now() - jiff::SignedDuration::from_hours(24 * 7);
+    let purged = purge::purge(&mut db, week_ago).await.unwrap();
+    assert_eq!(purged, purge::Purged::default());
+    let response = send("DELETE", format!("/user/{}", alice.id), key.clone()).await;
+    assert_eq!(response.status(), axum::http::StatusCode::OK);
+    let later = jiff::Timestamp::now() + jiff::SignedDuration::from_secs(1);
+    let purged = purge::purge(&mut db, later).await.unwrap();
+    assert_eq!(purged, purge::Purged { users: 1, jokes: 2 });
+    let response = send("POST", restore_alice, key.clone()).await;
+    assert_eq!(response.status(), axum::http::StatusCode::NOT_FOUND);
+    let response = send("POST", restore_first, key).await;
+    assert_eq!(response.status(), axum::http::StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_joke_revisions() {
+    let (app, mut db, key) = setup_with_db().await;
+    let alice = create_user(app.clone(), &key, "Alice", "alice@example.com").await;
+    let bob = create_user(app.clone(), &key, "Bob", "bob@example.com").await;
+    let alice_key = user_key(&mut db, alice.id).await;
+    let bob_key = user_key(&mut db, bob.id).await;
+    let joke = create_joke(app.clone(), &alice_key, alice.id, "Original").await;
+
+    let edit = |content: &'static str| {
+        let app = app.clone();
+        let alice_key = alice_key.clone();
+        async move {
+            let req = Request::builder()
+                .method("PATCH")
+                .uri(format!("/joke/{}", joke.id))
+                .header("authorization", format!("Bearer {alice_key}"))
+                .header("content-type", "application/json")
+                .body(Body::from(
+                    serde_json::json!({ "content": content }).to_string(),
+                ))
+                .unwrap();
+            assert_eq!(
+                app.oneshot(req).await.unwrap().status(),
+                axum::http::StatusCode::OK
+            );
+        }
+    };
+    let revisions = || {
+        let app = app.clone();
+        async move {
+            let get_req = Request::builder()
+                .uri(format!("/joke/{}/revisions", joke.id))
+                .body(Body::empty())
+                .unwrap();
+            let response = app.oneshot(get_req).await.unwrap();
+            assert_eq!(response.status(), axum::http::StatusCode::OK);
+            let page: serde_json::Value = json_body(response).await;
+            page["items"].as_array().unwrap().clone()
+        }
+    };
+    let revert = |key: String, rev: i64| {
+        let app = app.clone();
+        async move {
+            let req = Request::builder()
+                .method("POST")
+                .uri(format!("/joke/{}/revisions/{rev}/revert", joke.id))
+                .header("authorization", format!("Bearer {key}"))
+                .body(Body::empty())
+                .unwrap();
+            app.oneshot(req).await.unwrap()
+        }
+    };
+
+    edit("Second draft").await;
+    edit("Second draft").await;
+    edit("Third draft").await;
+    let history = revisions().await;
+    let contents: Vec<&str> = history
+        .iter()
+        .map(|rev| rev["content"].as_str().unwrap())
+        .collect();
+    assert_eq!(contents, ["Original", "Second draft", "Third draft"]);
+    assert!(history.iter().all(|rev| rev["author_id"] == alice.id));
+    let original = history[0]["id"].as_i64().unwrap();
+
+    let response = revert(bob_key, original).await;
+    assert_eq!(response.status(), axum::http::StatusCode::FORBIDDEN);
+    let other = create_joke(app.clone(), &key, bob.id, "Other").await;
+    let response = revert(alice_key.clone(), original + 100).await;
+    assert_eq!(response.status(), axum::http::StatusCode::NOT_FOUND);
+    let get_req = Request::builder()
+        .uri(format!("/joke/{}/revisions", other.id))
+        .body(Body::empty())
+        .unwrap();
+    let page: serde_json::Value = json_body(app.clone().oneshot(get_req).await.unwrap()).await;
+    assert_eq!(page["items"][0]["author_id"], bob.id);
+
+    // Reverting is recorded as a new revision, here by an operator key.
+    let response = revert(key.clone(), original).await;
+    assert_eq!(response.status(), axum::http::StatusCode::OK);
+    let reverted: Joke = json_body(response).await;
+    assert_eq!(reverted.content, "Original");
+    let history = revisions().await;
+    assert_eq!(history.len(), 4);
+    assert_eq!(history[3]["content"], "Original");
+    assert_eq!(history[3]["author_id"], serde_json::Value::Null);
+
+    // A joke from before revisions were kept gets its content recorded as of
+    // its last change, not its creation.
+    toasty::sql::statement("DELETE FROM joke_revisions WHERE joke_id = ?1")
+        .bind(other.id)
+        .exec(&mut db)
+        .await
+        .unwrap();
+    let response = patch_json(
+        app.clone(),
+        &key,
+        &format!("/joke/{}", other.id),
+        "application/json",
+        serde_json::json!({}),
+    )
+    .await;
+    assert_eq!(response.status(), axum::http::StatusCode::OK);
+    let touched = Joke::get_by_id(&mut db, other.id).await.unwrap();
+    assert_ne!(touched.updated_at, touched.created_at);
+    let response = patch_json(
+        app.clone(),
+        &key,
+        &format!("/joke/{}", other.id),
+        "application/json",
+        serde_json::json!({ "content": "Other, edited" }),
+    )
+    .await;
+    assert_eq!(response.status(), axum::http::StatusCode::OK);
+    let get_req = Request::builder()
+        .uri(format!("/joke/{}/revisions", other.id))
+        .body(Body::empty())
+        .unwrap();
+    let page: serde_json::Value = json_body(app.oneshot(get_req).await.unwrap()).await;
+    assert_eq!(page["items"][0]["content"], "Other");
+    let written_at: jiff::Timestamp = page["items"][0]["written_at"]
+        .as_str()
+        .unwrap()
+        .parse()
+        .unwrap();
+    assert_eq!(written_at, touched.updated_at);
+}
+
+/// Drop `column` from `table` as if the database predated it, along with the
+/// indices on it.
+async fn drop_column(db: &mut toasty::Db, table: &str, column: &str) {
+    let indices = toasty::sql::query(
+        "SELECT list.name FROM pragma_index_list(?1) AS list \
+         JOIN pragma_index_info(list.name) AS info WHERE info.name = ?2",
+    )
+    .bind(table.to_string())
+    .bind(column.to_string())
+    .column_types([toasty::stmt::Type::String])
+    .exec(db)
+    .await
+    .unwrap();
+    for index in indices {
+        let name = index.as_record().unwrap()[0].as_str().unwrap().to_string();
+        toasty::sql::statement(format!("DROP INDEX \"{name}\""))
+            .exec(db)
+            .await
+            .unwrap();
+    }
+    toasty::sql::statement(format!("ALTER TABLE {table} DROP COLUMN {column}"))
+        .exec(db)
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn test_upgrade_adds_missing_tables_and_columns() {
+    let (app, mut db, key) = setup_with_db().await;
+    assert!(migrate::upgrade(&mut db).await.unwrap().is_empty());
+    assert!(migrate::missing(&mut db).await.unwrap().is_empty());
+    let user = create_user(app.clone(), &key, "Alice", "alice@example.com").await;
+    let joke = create_joke(app, &key, user.id, "Why did the chicken cross the road?").await;
+    let definitions = |mut db: toasty::Db| async move {
+        toasty::sql::query("SELECT sql FROM sqlite_master WHERE tbl_name = 'tags' ORDER BY name")
+            .column_types([toasty::stmt::Type::String])
+            .exec(&mut db)
+            .await
+            .unwrap()
+    };
+    let created = definitions(db.clone()).await;
+    assert_eq!(created.len(), 2);
+    let indices = |mut db: toasty::Db| async move {
+        toasty::sql::query(
+            "SELECT sql FROM sqlite_master WHERE type = 'index' AND tbl_name = 'jokes' \
+             ORDER BY name",
+        )
+        .column_types([toasty::stmt::Type::String])
+        .exec(&mut db)
+        .await
+        .unwrap()
+    };
+    let indexed = indices(db.clone()).await;
+
+    drop_column(&mut db, "users", "role").await;
+    toasty::sql::statement("DROP TABLE tags")
+        .exec(&mut db)
+        .await
+        .unwrap();
+    let missing = migrate::missing(&mut db).await.unwrap();
+    assert_eq!(missing, ["users.role", "tags"]);
+
+    // Tables come back as `push_schema` made them, indices included, and
+    // existing rows get the model defaults.
+    assert_eq!(
+        migrate::upgrade(&mut db).await.unwrap(),
+        ["users.role", "tags"]
+    );
+    assert_eq!(definitions(db.clone()).await, created);
+    assert!(migrate::upgrade(&mut db).await.unwrap().is_empty());
+    assert!(migrate::missing(&mut db).await.unwrap().is_empty());
+    let user = User::get_by_id(&mut db, user.id).await.unwrap();
+    assert_eq!(user.role, Role::User);
+
+    let columns = [
+        "jokes.score",
+        "jokes.favorite_count",
+        "jokes.reaction_counts",
+        "jokes.hidden",
+        "jokes.deleted_at",
+    ];
+    for column in columns {
+        let (table, column) = column.split_once('.').unwrap();
+        drop_column(&mut db, table, column).await;
+    }
+    assert_eq!(migrate::upgrade(&mut db).await.unwrap(), columns);
+    assert_eq!(indices(db.clone()).await, indexed);
+    let joke = Joke::get_by_id(&mut db, joke.id).await.unwrap();
+    assert_eq!(joke.score, 0);
+    assert_eq!(joke.favorite_count, 0);
+    assert!(joke.reaction_counts.0.is_empty());
+    assert!(!joke.hidden);
+    assert!(joke.deleted_at.is_none());
+
+    // A required column without a default is left for `missing` to report.
+    drop_column(&mut db, "users", "name").await;
+    assert!(migrate::upgrade(&mut db).await.unwrap().is_empty());
+    assert_eq!(migrate::missing(&mut db).await.unwrap(), ["users.name"]);
+}